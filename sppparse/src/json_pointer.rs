@@ -0,0 +1,188 @@
+use super::*;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+/// The characters to percent-encode in a URI fragment, as per [RFC 3986](https://tools.ietf.org/html/rfc3986#section-3.5)
+const FRAGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'[')
+    .add(b'\\')
+    .add(b']')
+    .add(b'^')
+    .add(b'`')
+    .add(b'{')
+    .add(b'|')
+    .add(b'}');
+
+/// # A parsed [JSON Pointer](https://tools.ietf.org/html/rfc6901)
+///
+/// The pointer is stored as a list of unescaped reference tokens. It's escaped
+/// again (`~` to `~0` and `/` to `~1`) when displayed, so that it round-trips.
+///
+/// The empty pointer (`""`) references the whole document.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct JsonPointer {
+    tokens: Vec<String>,
+}
+
+impl JsonPointer {
+    /// Create a new [JsonPointer](JsonPointer) referencing the whole document
+    pub fn new() -> Self {
+        JsonPointer::default()
+    }
+
+    /// Parse an escaped JSON pointer
+    pub fn parse(pointer: &str) -> Result<Self, SparseError> {
+        if pointer.is_empty() {
+            return Ok(JsonPointer::new());
+        }
+        if !pointer.starts_with('/') {
+            return Err(SparseError::InvalidPointer(pointer.to_string()));
+        }
+        let tokens = pointer[1..]
+            .split('/')
+            .map(JsonPointer::unescape)
+            .collect::<Option<Vec<String>>>()
+            .ok_or_else(|| SparseError::InvalidPointer(pointer.to_string()))?;
+        Ok(JsonPointer { tokens })
+    }
+
+    /// Parse a percent-encoded JSON pointer, as found in a URI fragment
+    ///
+    /// i.e. `/components/schemas/My%20Type`
+    pub fn parse_uri_fragment(fragment: &str) -> Result<Self, SparseError> {
+        let decoded = percent_decode_str(fragment)
+            .decode_utf8()
+            .map_err(|_e| SparseError::InvalidPointer(fragment.to_string()))?;
+        JsonPointer::parse(&decoded)
+    }
+
+    /// Serialize the pointer as a percent-encoded URI fragment, including the leading `#`
+    pub fn to_uri_fragment(&self) -> String {
+        format!("#{}", utf8_percent_encode(&self.to_string(), FRAGMENT))
+    }
+
+    /// Escape a reference token, replacing `~` by `~0` and `/` by `~1`
+    pub fn escape(token: &str) -> String {
+        token.replace('~', "~0").replace('/', "~1")
+    }
+
+    /// Unescape a reference token, replacing `~1` by `/` and `~0` by `~`.
+    ///
+    /// Returns `None` if the token contains an invalid escape sequence.
+    pub fn unescape(token: &str) -> Option<String> {
+        let mut res = String::with_capacity(token.len());
+        let mut chars = token.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '~' => match chars.next() {
+                    Some('0') => res.push('~'),
+                    Some('1') => res.push('/'),
+                    _ => return None,
+                },
+                _ => res.push(c),
+            }
+        }
+        Some(res)
+    }
+
+    /// The unescaped reference tokens of the pointer
+    pub fn tokens(&self) -> &[String] {
+        &self.tokens
+    }
+
+    /// True if the pointer references the whole document
+    pub fn is_root(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    /// Append an unescaped reference token to the pointer
+    pub fn push<T: Into<String>>(&mut self, token: T) {
+        self.tokens.push(token.into());
+    }
+
+    /// Remove the last reference token of the pointer, returning it
+    pub fn pop(&mut self) -> Option<String> {
+        self.tokens.pop()
+    }
+
+    /// Parse an array index as specified by the RFC (no sign, no leading zeros)
+    fn parse_index(token: &str) -> Option<usize> {
+        if token.starts_with('+') || (token.len() > 1 && token.starts_with('0')) {
+            return None;
+        }
+        token.parse().ok()
+    }
+
+    /// Evaluate the pointer against a [Value](serde_json::Value)
+    pub fn get<'a>(&self, val: &'a Value) -> Option<&'a Value> {
+        self.tokens
+            .iter()
+            .try_fold(val, |target, token| match target {
+                Value::Object(map) => map.get(token),
+                Value::Array(list) => JsonPointer::parse_index(token).and_then(|x| list.get(x)),
+                _ => None,
+            })
+    }
+
+    /// Evaluate the pointer against a [Value](serde_json::Value) (mutable)
+    pub fn get_mut<'a>(&self, val: &'a mut Value) -> Option<&'a mut Value> {
+        self.tokens
+            .iter()
+            .try_fold(val, |target, token| match target {
+                Value::Object(map) => map.get_mut(token),
+                Value::Array(list) => {
+                    JsonPointer::parse_index(token).and_then(move |x| list.get_mut(x))
+                }
+                _ => None,
+            })
+    }
+}
+
+impl Display for JsonPointer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for token in self.tokens.iter() {
+            write!(f, "/{}", JsonPointer::escape(token))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for JsonPointer {
+    type Err = SparseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        JsonPointer::parse(s)
+    }
+}
+
+impl PartialEq<str> for JsonPointer {
+    fn eq(&self, other: &str) -> bool {
+        JsonPointer::parse(other).is_ok_and(|other| *self == other)
+    }
+}
+
+impl PartialEq<&str> for JsonPointer {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+impl PartialEq<JsonPointer> for str {
+    fn eq(&self, other: &JsonPointer) -> bool {
+        other == self
+    }
+}
+
+impl PartialEq<JsonPointer> for &str {
+    fn eq(&self, other: &JsonPointer) -> bool {
+        other == *self
+    }
+}
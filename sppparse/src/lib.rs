@@ -219,21 +219,37 @@
 //! ```
 #![warn(clippy::all)]
 
+mod json_pointer;
 mod sparsable;
+mod sparse_bundle;
+mod sparse_dereference;
+mod sparse_document_id;
 mod sparse_errors;
+mod sparse_lazy_value;
+mod sparse_limits;
+mod sparse_loader;
 mod sparse_metadata;
+mod sparse_patch;
 mod sparse_pointed_value;
 mod sparse_pointer;
+mod sparse_policy;
+mod sparse_raw_pointer;
 mod sparse_ref;
+mod sparse_ref_lazy;
 mod sparse_ref_raw;
 mod sparse_ref_raw_inline;
 mod sparse_root;
 mod sparse_selector;
+mod sparse_span;
 mod sparse_state;
+mod sparse_state_builder;
+mod sparse_sync;
 mod sparse_value;
 mod sparse_value_mut;
 
-/// The max stack frames [Sparse](crate) will go before returning a [cyclic](crate::SparseError::CyclicRef).
+/// The default max stack frames [Sparse](crate) will go before returning a [MaxDepthReached](crate::SparseError::MaxDepthReached).
+///
+/// It can be changed for each [SparseState](crate::SparseState) using [set_max_depth](crate::SparseState::set_max_depth).
 ///
 /// For each [SparseSelector](crate::SparseSelector) in your objects, you should count 3 stack frames.
 ///
@@ -244,26 +260,41 @@ pub const MAX_SPARSE_DEPTH: u32 = 100;
 #[cfg(test)]
 pub(crate) mod tests;
 
+pub use crate::json_pointer::JsonPointer;
+pub use crate::sparse_dereference::SparseCycleBehavior;
 pub use crate::sparse_errors::SparseError;
+pub use crate::sparse_limits::{SparseLimit, SparseLimits};
+#[cfg(feature = "url")]
+pub use crate::sparse_loader::SparseUrlDirLoader;
+pub use crate::sparse_loader::{SparseFileLoader, SparseLoader, SparseMemoryFs};
+pub(crate) use crate::sparse_raw_pointer::SparseRawPointer;
 pub use crate::sparse_state::{SparseFileFormat, SparseState, SparseStateFile};
+pub use crate::sparse_state_builder::{SparseRootBuilder, SparseStateBuilder};
+pub(crate) use crate::sparse_sync::{SparseAnyRc, SparseOnceCell, SparseWeak};
+pub use crate::sparse_sync::{
+    SparseCell, SparseRc, SparseSendSync, SparseStateBorrow, SparseStateCell, SparseStateRef,
+    SparseStateRefMut,
+};
 use getset::{CopyGetters, Getters, MutGetters};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::Value;
 pub use sparsable::Sparsable as SparsableTrait;
+pub use sparse_lazy_value::SparseLazyValue;
 pub use sparse_metadata::SparseMetadata;
 pub use sparse_pointed_value::SparsePointedValue;
 pub use sparse_pointer::{SparsePointer, SparsePointerRaw};
+pub use sparse_policy::SparsePolicy;
 pub use sparse_ref::SparseRef;
+pub use sparse_ref_lazy::SparseRefLazy;
 pub use sparse_ref_raw::SparseRefRaw;
 pub use sparse_ref_raw_inline::SparseRefRawInline;
 pub use sparse_root::SparseRoot;
 pub use sparse_selector::SparseSelector;
+pub use sparse_span::{SparseLocation, SparseSpan};
 pub use sparse_value::SparseValue;
 pub use sparse_value_mut::SparseValueMut;
 pub use sppparse_derive::Sparsable;
 
-use std::cell::RefCell;
 use std::collections::HashMap;
 use std::convert::From;
 use std::path::PathBuf;
-use std::rc::Rc;
@@ -1,4 +1,5 @@
 use super::*;
+use indexmap::IndexMap;
 use std::collections::*;
 use std::ffi::CString;
 
@@ -28,12 +29,12 @@ pub trait Sparsable {
         self.sparse_init(state, metadata, depth)
     }
 
-    /// Check if the current depth isn't too much.
-    /// This is the cyclic pointer protection mechanism
-    fn check_depth(&self, depth: u32) -> Result<(), SparseError> {
-        match depth < MAX_SPARSE_DEPTH {
+    /// Check if the current depth isn't more than the maximum depth of the state.
+    /// This is the stack overflow protection mechanism
+    fn check_depth(&self, state: &SparseState, depth: u32) -> Result<(), SparseError> {
+        match depth < state.max_depth() {
             true => Ok(()),
-            false => Err(SparseError::CyclicRef),
+            false => Err(SparseError::MaxDepthReached(state.max_depth())),
         }
     }
 }
@@ -110,9 +111,8 @@ where
         metadata: &SparseMetadata,
         depth: u32,
     ) -> Result<(), SparseError> {
-        match self {
-            Some(x) => x.sparse_init(state, metadata, depth + 1)?,
-            None => (),
+        if let Some(x) = self {
+            x.sparse_init(state, metadata, depth + 1)?
         };
         Ok(())
     }
@@ -123,15 +123,14 @@ where
         metadata: &SparseMetadata,
         depth: u32,
     ) -> Result<(), SparseError> {
-        match self {
-            Some(x) => x.sparse_updt(state, metadata, depth + 1)?,
-            None => (),
+        if let Some(x) = self {
+            x.sparse_updt(state, metadata, depth + 1)?
         };
         Ok(())
     }
 }
 
-impl<'a> Sparsable for &'a str {
+impl Sparsable for &str {
     fn sparse_init(
         &mut self,
         _state: &mut SparseState,
@@ -142,7 +141,7 @@ impl<'a> Sparsable for &'a str {
     }
 }
 
-impl<'a> Sparsable for &'a [u8] {
+impl Sparsable for &[u8] {
     fn sparse_init(
         &mut self,
         _state: &mut SparseState,
@@ -153,22 +152,26 @@ impl<'a> Sparsable for &'a [u8] {
     }
 }
 
-impl<K, V> Sparsable for HashMap<K, V>
-where
-    V: Sparsable,
-{
-    fn sparse_init(
-        &mut self,
-        state: &mut SparseState,
-        metadata: &SparseMetadata,
-        depth: u32,
-    ) -> Result<(), SparseError> {
-        let ndepth = depth + 1;
-        for i in self.values_mut() {
-            i.sparse_init(state, metadata, ndepth)?;
+macro_rules! impl_sparsable_map {
+    ($x:ident) => {
+        impl<K, V> Sparsable for $x<K, V>
+        where
+            V: Sparsable,
+        {
+            fn sparse_init(
+                &mut self,
+                state: &mut SparseState,
+                metadata: &SparseMetadata,
+                depth: u32,
+            ) -> Result<(), SparseError> {
+                let ndepth = depth + 1;
+                for i in self.values_mut() {
+                    i.sparse_init(state, metadata, ndepth)?;
+                }
+                Ok(())
+            }
         }
-        Ok(())
-    }
+    };
 }
 
 macro_rules! impl_sparsable_iter {
@@ -210,6 +213,9 @@ impl_sparsable_nothing!(f64);
 impl_sparsable_nothing!(char);
 impl_sparsable_nothing!(String);
 impl_sparsable_nothing!(CString);
+impl_sparsable_map!(HashMap);
+impl_sparsable_map!(BTreeMap);
+impl_sparsable_map!(IndexMap);
 impl_sparsable_iter!(Vec);
 impl_sparsable_iter!(VecDeque);
 impl_sparsable_iter!(LinkedList);
@@ -0,0 +1,144 @@
+use super::*;
+use crate::sparse_document_id::SparseDocumentId;
+use std::collections::HashSet;
+
+/// The default section of the bundled document in which the distant documents are copied
+const SPARSE_BUNDLE_SECTION: &str = "/definitions";
+
+/// # Rewrite the references of the documents of a state to a single document
+#[derive(Debug)]
+struct SparseBundler {
+    /// The id of the root document
+    root: SparseDocumentId,
+    /// The section in which the distant documents are copied
+    section: JsonPointer,
+    /// The name given to each distant document in the section
+    names: HashMap<SparseDocumentId, String>,
+    /// The names that can't be used in the section anymore
+    taken: HashSet<String>,
+    /// The distant documents to copy in the section
+    queue: Vec<(SparseMetadata, String)>,
+}
+
+impl SparseBundler {
+    /// Get the name of a distant document in the section, queuing it if it's the first time it's referenced
+    fn name_for(&mut self, id: SparseDocumentId, utils: &SparseMetadata) -> String {
+        if let Some(name) = self.names.get(&id) {
+            return name.clone();
+        }
+        let base = id.name();
+        let mut name = base.clone();
+        let mut idx: usize = 1;
+        while self.taken.contains(&name) {
+            name = format!("{}_{}", base, idx);
+            idx += 1;
+        }
+        self.taken.insert(name.clone());
+        self.names.insert(id, name.clone());
+        self.queue.push((utils.clone(), name.clone()));
+        name
+    }
+
+    /// Rewrite every reference of a value, originating from the document described by `parent`
+    fn rewrite(&mut self, val: &mut Value, parent: &SparseMetadata) -> Result<(), SparseError> {
+        match val {
+            Value::Object(map) => {
+                if let Some(Value::String(raw_ptr)) = map.get("$ref") {
+                    let utils = SparseMetadata::new_from_parent(raw_ptr.clone(), parent)?;
+                    let id = SparseDocumentId::new(&utils);
+                    let pointer = match id == self.root {
+                        true => utils.pointer().clone(),
+                        false => {
+                            let mut pointer = self.section.clone();
+                            pointer.push(self.name_for(id, &utils));
+                            for token in utils.pointer().tokens() {
+                                pointer.push(token.clone());
+                            }
+                            pointer
+                        }
+                    };
+                    map.insert(
+                        String::from("$ref"),
+                        Value::String(pointer.to_uri_fragment()),
+                    );
+                }
+                for inner in map.values_mut() {
+                    self.rewrite(inner, parent)?;
+                }
+            }
+            Value::Array(list) => {
+                for inner in list.iter_mut() {
+                    self.rewrite(inner, parent)?;
+                }
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+}
+
+impl SparseState {
+    /// Bundle the root document and every document it references into a single [Value](serde_json::Value).
+    ///
+    /// The distant documents are copied in the `definitions` section of the root document and
+    /// the references are rewritten to local pointers into that section.
+    pub fn bundle(&mut self) -> Result<Value, SparseError> {
+        self.bundle_into(&JsonPointer::parse(SPARSE_BUNDLE_SECTION)?)
+    }
+
+    /// Like `bundle` but copy the distant documents into the specified section of the root document.
+    ///
+    /// Each document is named after its file name, suffixed if that name is already
+    /// used in the section.
+    pub fn bundle_into(&mut self, section: &JsonPointer) -> Result<Value, SparseError> {
+        let root_utils = SparseMetadata::new(String::from("#"), self.get_root_path().clone())?;
+        let mut res: Value = self.get_state_file(self.get_root_path())?.val().clone();
+        let mut bundler = SparseBundler {
+            root: SparseDocumentId::new(&root_utils),
+            section: section.clone(),
+            names: HashMap::new(),
+            taken: match section.get(&res) {
+                Some(Value::Object(map)) => map.keys().cloned().collect(),
+                _ => HashSet::new(),
+            },
+            queue: Vec::new(),
+        };
+        let mut documents: Vec<(String, Value)> = Vec::new();
+
+        bundler.rewrite(&mut res, &root_utils)?;
+        while let Some((utils, name)) = bundler.queue.pop() {
+            self.add_file_for(&utils)?;
+            let mut val = self.get_state_file_for(&utils)?.val().clone();
+            bundler.rewrite(&mut val, &utils)?;
+            documents.push((name, val));
+        }
+        if documents.is_empty() {
+            return Ok(res);
+        }
+        let section_val = SparseState::get_or_create_section(&mut res, section)?;
+        for (name, val) in documents.into_iter() {
+            section_val.insert(name, val);
+        }
+        Ok(res)
+    }
+
+    /// Get the section of a document as an object, creating it if it doesn't exist
+    fn get_or_create_section<'a>(
+        val: &'a mut Value,
+        section: &JsonPointer,
+    ) -> Result<&'a mut serde_json::Map<String, Value>, SparseError> {
+        let mut curr = val;
+        for token in section.tokens() {
+            curr = match curr {
+                Value::Object(map) => map
+                    .entry(token.clone())
+                    .or_insert_with(|| Value::Object(serde_json::Map::new())),
+                _ => return Err(SparseError::InvalidBundleSection(section.to_string())),
+            };
+        }
+        match curr {
+            Value::Object(map) => Ok(map),
+            _ => Err(SparseError::InvalidBundleSection(section.to_string())),
+        }
+    }
+}
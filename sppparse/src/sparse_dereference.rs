@@ -0,0 +1,164 @@
+use super::*;
+use crate::sparse_document_id::SparseDocumentId;
+use crate::sparse_state::count_values;
+
+/// # What to do when a cyclic reference is met while dereferencing a document
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SparseCycleBehavior {
+    /// Fail with [CyclicRef](crate::SparseError::CyclicRef)
+    #[default]
+    Error,
+    /// Keep the reference, rewritten to point to the location where its target was inlined
+    KeepRef,
+    /// Unroll the cycles, keeping the original reference once that many references are nested
+    CutOff(u32),
+}
+
+/// # Inline the references of the documents of a state
+#[derive(Debug)]
+struct SparseDereferencer<'a> {
+    state: &'a mut SparseState,
+    behavior: SparseCycleBehavior,
+    /// The references being inlined, with the location of their target in the output
+    visiting: Vec<(SparseDocumentId, JsonPointer, JsonPointer)>,
+}
+
+impl<'a> SparseDereferencer<'a> {
+    /// Inline the target of a reference, originating from the document described by `parent`
+    fn inline_ref(
+        &mut self,
+        val: Value,
+        raw_ptr: String,
+        parent: &SparseMetadata,
+        location: &mut JsonPointer,
+    ) -> Result<Value, SparseError> {
+        let utils = SparseMetadata::new_from_parent(raw_ptr, parent)?;
+        let id = SparseDocumentId::new(&utils);
+        let cycle = self
+            .visiting
+            .iter()
+            .position(|(visited_id, visited_ptr, _)| {
+                *visited_id == id && visited_ptr == utils.pointer()
+            });
+
+        match (self.behavior, cycle) {
+            (SparseCycleBehavior::Error, Some(pos)) => {
+                let mut chain: Vec<String> = self.visiting[pos..]
+                    .iter()
+                    .map(|(visited_id, visited_ptr, _)| visited_id.describe(visited_ptr))
+                    .collect();
+                chain.push(id.describe(utils.pointer()));
+                return Err(SparseError::CyclicRef(chain));
+            }
+            (SparseCycleBehavior::KeepRef, Some(pos)) => {
+                let visited_ptr = Value::String(self.visiting[pos].2.to_uri_fragment());
+                // An inline reference is a bare string
+                if val.is_string() {
+                    return Ok(visited_ptr);
+                }
+                let mut res = serde_json::Map::new();
+                res.insert(String::from("$ref"), visited_ptr);
+                return Ok(Value::Object(res));
+            }
+            (SparseCycleBehavior::CutOff(max), _) if self.visiting.len() > max as usize => {
+                return Ok(val)
+            }
+            _ => (),
+        }
+        self.state.count_ref()?;
+        self.state.add_file_for(&utils)?;
+        let target = utils
+            .pointer()
+            .get(self.state.get_state_file_for(&utils)?.val())
+            .ok_or_else(|| SparseError::UnkownPath(utils.pointer().to_string()))?
+            .clone();
+        self.state.count_expansion(count_values(&target))?;
+        self.visiting
+            .push((id, utils.pointer().clone(), location.clone()));
+        let res = self.inline(target, &utils, &mut utils.pointer().clone(), location);
+        self.visiting.pop();
+        res
+    }
+
+    /// Inline every reference of a value, originating from the document described by `parent`.
+    ///
+    /// The value lives at `source` in its document, and at `location` in the output.
+    fn inline(
+        &mut self,
+        val: Value,
+        parent: &SparseMetadata,
+        source: &mut JsonPointer,
+        location: &mut JsonPointer,
+    ) -> Result<Value, SparseError> {
+        match val {
+            Value::Object(map) => {
+                if let Some(Value::String(raw_ptr)) = map.get("$ref") {
+                    let raw_ptr = raw_ptr.clone();
+                    return self.inline_ref(Value::Object(map), raw_ptr, parent, location);
+                }
+                let mut res = serde_json::Map::new();
+                for (key, inner) in map.into_iter() {
+                    source.push(key.clone());
+                    location.push(key.clone());
+                    let inner = self.inline(inner, parent, source, location)?;
+                    location.pop();
+                    source.pop();
+                    res.insert(key, inner);
+                }
+                Ok(Value::Object(res))
+            }
+            Value::Array(list) => {
+                let mut res = Vec::with_capacity(list.len());
+                for (idx, inner) in list.into_iter().enumerate() {
+                    source.push(idx.to_string());
+                    location.push(idx.to_string());
+                    res.push(self.inline(inner, parent, source, location)?);
+                    location.pop();
+                    source.pop();
+                }
+                Ok(Value::Array(res))
+            }
+            Value::String(raw_ptr)
+                if self
+                    .state
+                    .is_inline_ref(&SparseDocumentId::new(parent), source) =>
+            {
+                self.inline_ref(Value::String(raw_ptr.clone()), raw_ptr, parent, location)
+            }
+            _ => Ok(val),
+        }
+    }
+}
+
+impl SparseState {
+    /// Export the root document as a [Value](serde_json::Value), replacing every reference
+    /// by the value it's pointing to.
+    ///
+    /// The [SparseRefRawInline](crate::SparseRefRawInline) being bare strings, they're
+    /// only replaced once the document has been parsed, using
+    /// [parse_root](crate::SparseState::parse_root) for instance.
+    pub fn to_dereferenced_value(
+        &mut self,
+        behavior: SparseCycleBehavior,
+    ) -> Result<Value, SparseError> {
+        let root_utils = SparseMetadata::new(String::from("#"), self.get_root_path().clone())?;
+        let root: Value = self.get_state_file(self.get_root_path())?.val().clone();
+        self.reset_counts();
+        let mut dereferencer = SparseDereferencer {
+            state: self,
+            behavior,
+            visiting: vec![(
+                SparseDocumentId::new(&root_utils),
+                JsonPointer::new(),
+                JsonPointer::new(),
+            )],
+        };
+
+        dereferencer.inline(
+            root,
+            &root_utils,
+            &mut JsonPointer::new(),
+            &mut JsonPointer::new(),
+        )
+    }
+}
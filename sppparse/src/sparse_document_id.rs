@@ -0,0 +1,57 @@
+use super::*;
+use path_clean::PathClean;
+use std::fmt::{self, Display};
+
+#[cfg(feature = "url")]
+use url_inner::Url;
+
+/// # Identify a document of the state
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) enum SparseDocumentId {
+    Path(PathBuf),
+    #[cfg(feature = "url")]
+    Url(Url),
+}
+
+impl SparseDocumentId {
+    /// Get the id of the document a [SparseMetadata](crate::SparseMetadata) is pointing to
+    pub(crate) fn new(utils: &SparseMetadata) -> Self {
+        #[cfg(feature = "url")]
+        if let Some(url) = utils.pfile_url() {
+            return SparseDocumentId::Url(url.clone());
+        }
+        SparseDocumentId::Path(utils.pfile_path().clean())
+    }
+
+    /// Describe a pointer inside the document, i.e. `/path/to/file.json#/hello`
+    pub(crate) fn describe(&self, pointer: &JsonPointer) -> String {
+        format!("{}{}", self, pointer.to_uri_fragment())
+    }
+
+    /// A human readable name for the document, without its extension
+    pub(crate) fn name(&self) -> String {
+        let name = match self {
+            SparseDocumentId::Path(path) => path.file_stem().map(|x| x.to_string_lossy()),
+            #[cfg(feature = "url")]
+            SparseDocumentId::Url(url) => url
+                .path_segments()
+                .and_then(|mut x| x.next_back())
+                .and_then(|x| std::path::Path::new(x).file_stem())
+                .map(|x| x.to_string_lossy()),
+        };
+        match name {
+            Some(name) if !name.is_empty() => name.into_owned(),
+            _ => String::from("document"),
+        }
+    }
+}
+
+impl Display for SparseDocumentId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SparseDocumentId::Path(path) => write!(f, "{}", path.display()),
+            #[cfg(feature = "url")]
+            SparseDocumentId::Url(url) => write!(f, "{}", url),
+        }
+    }
+}
@@ -1,11 +1,18 @@
+use super::*;
 use thiserror::Error;
 
 /// # An error throwable by [Sparse](crate)
+///
+/// The `toml` and `json5` features add variants, so it's non exhaustive.
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum SparseError {
     /// When the JSON Pointer point to `undefined`
     #[error("The JSON pointer `{0}` is undefined")]
     UnkownPath(String),
+    /// When the JSON Pointer is not a valid RFC 6901 pointer
+    #[error("The JSON pointer `{0}` is invalid")]
+    InvalidPointer(String),
     /// When the value of a pointer has changed
     #[error("The value pointed by this pointer has changed since the last deserialization")]
     OutdatedPointer,
@@ -18,12 +25,18 @@ pub enum SparseError {
     /// Prevent the RefCell from panicking
     #[error("The inner state is already mutably borrowed elsewhere")]
     StateAlreadyBorrowed,
+    /// When a thread panicked while holding the state, with the `sync` feature
+    #[error("The inner state is poisoned")]
+    StatePoisoned,
     /// When the state is not capable of accepting distant file in a pointer
     #[error("Referencing distant file is not possible for local reference")]
     NoDistantFile,
-    /// When there is a recursive pointer
-    #[error("A cyclic reference was stopped")]
-    CyclicRef,
+    /// When there is a recursive pointer, with the chain of references forming the cycle
+    #[error("A cyclic reference was stopped: {}", .0.join(" -> "))]
+    CyclicRef(Vec<String>),
+    /// When the resolution of a document goes deeper than the maximum depth of the [SparseState](crate::SparseState)
+    #[error("The maximum depth of {0} was reached")]
+    MaxDepthReached(u32),
     /// One of the limitation of [Sparse](crate) is the inability to modify root elements
     /// from a pointer referencing it.
     #[error("Sparse cannot mutate a root element via a SparseValue")]
@@ -34,15 +47,76 @@ pub enum SparseError {
     /// When changing the base path of a state.
     #[error("Cannot change the base path because it's already set")]
     ChangingExistingBasePath,
+    /// When the [SparseLoader](crate::SparseLoader) is not able to fetch a distant document
+    #[error("Cannot fetch the distant document `{0}`")]
+    UnsupportedUrl(String),
+    /// When saving a state in which a distant document was modified
+    #[error("Cannot save the modified distant document `{0}`")]
+    UnsavableUrl(String),
+    /// When a limit of the [SparseLimits](crate::SparseLimits) of the state is exceeded
+    #[error("The {limit} limit of {max} was exceeded")]
+    LimitExceeded {
+        /// The limit that was exceeded
+        limit: SparseLimit,
+        /// The max value of that limit
+        max: u64,
+    },
+    /// When the [SparsePolicy](crate::SparsePolicy) of the state forbids fetching a document
+    #[error("Fetching `{target}` is forbidden, {reason}")]
+    Forbidden {
+        /// The path or the URL of the document
+        target: String,
+        /// The rule of the policy that was violated
+        reason: String,
+    },
+    /// When a document can't be saved in its own format by patching its original text,
+    /// while the state is [lossless](crate::SparseState::set_lossless)
+    #[error("Cannot save `{0}` without losing its formatting")]
+    NotPatchable(String),
+    /// When the section in which the documents are bundled is not an object
+    #[error("Cannot bundle the documents in `{0}`, it's not an object")]
+    InvalidBundleSection(String),
     /// When a pointer points to a file that is not in the state
     #[error("File not in state")]
     NotInState,
+    /// When a value of a document in the state cannot be deserialized
+    #[error("Cannot deserialize `{file}#{pointer}` at `{path}`: {source}")]
+    Deserialize {
+        /// The path or the URL of the document
+        file: String,
+        /// The JSON pointer of the value being deserialized in the document
+        pointer: String,
+        /// The path inside the value where the deserialization failed
+        path: String,
+        /// The underlying error
+        source: serde_json::Error,
+    },
+    /// When a reference cannot be resolved, with the location of that reference
+    #[error("Cannot resolve the reference at `{location}`: {source}")]
+    BrokenRef {
+        /// Where the reference lives
+        location: SparseLocation,
+        /// The underlying error
+        source: Box<SparseError>,
+    },
     /// When there is a failure while deserializing the JSON
     #[error(transparent)]
     SerdeJson(#[from] serde_json::Error),
     /// When there is a failure while deserializing the YAML
     #[error(transparent)]
     SerdeYaml(#[from] serde_yaml::Error),
+    /// When there is a failure while deserializing the TOML
+    #[cfg(feature = "toml")]
+    #[error(transparent)]
+    SerdeToml(#[from] toml_inner::de::Error),
+    /// When there is a failure while serializing the TOML
+    #[cfg(feature = "toml")]
+    #[error(transparent)]
+    SerdeTomlSer(#[from] toml_inner::ser::Error),
+    /// When there is a failure while deserializing the JSON5
+    #[cfg(feature = "json5")]
+    #[error(transparent)]
+    SerdeJson5(#[from] json5_inner::Error),
     /// When there is an IO failure
     #[error(transparent)]
     Io(#[from] std::io::Error),
@@ -0,0 +1,49 @@
+use super::*;
+use std::ops::Deref;
+
+/// # A value resolved by a [SparseRefLazy](crate::SparseRefLazy)
+///
+/// The pointed value is shared with the [SparseState](crate::SparseState) and the other
+/// [SparseRefLazy](crate::SparseRefLazy) pointing to it. The [SparseLazyValue](SparseLazyValue)
+/// keeps it alive, even if the state is dropped or resolves it again after an update.
+#[derive(Debug, Clone)]
+pub struct SparseLazyValue<S> {
+    /// The shared pointed value
+    val: SparseRc<SparsePointedValue<S>>,
+    /// Metadata about the pointer
+    utils: SparseMetadata,
+}
+
+impl<S> SparseLazyValue<S>
+where
+    S: DeserializeOwned + Serialize + SparsableTrait,
+{
+    /// Create a new [SparseLazyValue](SparseLazyValue), failing if the pointed value can't be borrowed
+    pub(crate) fn new(
+        val: SparseRc<SparsePointedValue<S>>,
+        utils: SparseMetadata,
+    ) -> Result<Self, SparseError> {
+        val.get(Some(&utils))?;
+        Ok(SparseLazyValue { val, utils })
+    }
+
+    /// Get the pointed value, along with the metadata of the pointer
+    pub fn get(&self) -> SparseValue<'_, S> {
+        match self.val.get(Some(&self.utils)) {
+            Ok(res) => res,
+            // The shared value is read-only, it was checked at creation
+            Err(_) => unreachable!("The lazy value can't be borrowed"),
+        }
+    }
+}
+
+impl<S> Deref for SparseLazyValue<S>
+where
+    S: DeserializeOwned + Serialize + SparsableTrait,
+{
+    type Target = S;
+
+    fn deref(&self) -> &Self::Target {
+        self.get().sref()
+    }
+}
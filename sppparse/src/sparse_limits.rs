@@ -0,0 +1,119 @@
+use super::*;
+use std::fmt::{self, Display};
+use std::io::Read;
+
+/// # A limit of a [SparseLimits](crate::SparseLimits)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SparseLimit {
+    /// The size of a document, in bytes
+    FileSize,
+    /// The number of documents in the state
+    Files,
+    /// The number of references resolved at once
+    Refs,
+    /// The ratio between the number of values resolved through references and the number
+    /// of values of the documents
+    Expansion,
+}
+
+impl Display for SparseLimit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SparseLimit::FileSize => write!(f, "file size"),
+            SparseLimit::Files => write!(f, "number of files"),
+            SparseLimit::Refs => write!(f, "number of references"),
+            SparseLimit::Expansion => write!(f, "expansion ratio"),
+        }
+    }
+}
+
+/// # Limit the resources a [SparseState](crate::SparseState) can use
+///
+/// When loading untrusted documents, a huge file, a reference to every file of a
+/// directory or chained references expanding exponentially (a "billion laughs")
+/// could exhaust the resources of the process. Exceeding a limit fails with
+/// [LimitExceeded](crate::SparseError::LimitExceeded), naming that limit.
+///
+/// The references are counted from the start of the initialization of a document,
+/// using [SparseState::parse_root](crate::SparseState::parse_root) for instance, or
+/// of the resolution of a [SparseRefLazy](crate::SparseRefLazy).
+///
+/// The default limits don't restrict anything.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, CopyGetters)]
+pub struct SparseLimits {
+    /// The max size of a document, in bytes
+    #[getset(get_copy = "pub")]
+    max_file_size: Option<u64>,
+    /// The max number of documents in the state
+    #[getset(get_copy = "pub")]
+    max_files: Option<u64>,
+    /// The max number of references resolved at once
+    #[getset(get_copy = "pub")]
+    max_refs: Option<u64>,
+    /// The max ratio between the number of values resolved through references
+    /// and the number of values of the documents
+    #[getset(get_copy = "pub")]
+    max_expansion: Option<u64>,
+}
+
+impl SparseLimits {
+    /// Create a new [SparseLimits](crate::SparseLimits) without any limit
+    pub fn new() -> Self {
+        SparseLimits::default()
+    }
+
+    /// Limit the size of the documents, in bytes
+    pub fn limit_file_size(mut self, max: u64) -> Self {
+        self.max_file_size = Some(max);
+        self
+    }
+
+    /// Limit the number of documents in the state
+    pub fn limit_files(mut self, max: u64) -> Self {
+        self.max_files = Some(max);
+        self
+    }
+
+    /// Limit the number of references resolved at once
+    pub fn limit_refs(mut self, max: u64) -> Self {
+        self.max_refs = Some(max);
+        self
+    }
+
+    /// Limit the ratio between the number of values resolved through references and the
+    /// number of values of the documents, when initializing or dereferencing a document
+    pub fn limit_expansion(mut self, max: u64) -> Self {
+        self.max_expansion = Some(max);
+        self
+    }
+
+    /// Get the max value of a limit, if set
+    pub fn max(&self, limit: SparseLimit) -> Option<u64> {
+        match limit {
+            SparseLimit::FileSize => self.max_file_size,
+            SparseLimit::Files => self.max_files,
+            SparseLimit::Refs => self.max_refs,
+            SparseLimit::Expansion => self.max_expansion,
+        }
+    }
+
+    /// Check that `val` doesn't exceed a limit
+    pub fn check(&self, limit: SparseLimit, val: u64) -> Result<(), SparseError> {
+        match self.max(limit) {
+            Some(max) if val > max => Err(SparseError::LimitExceeded { limit, max }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Read a document from `reader`, failing as soon as it exceeds the max file size
+    pub fn read_to_end<R: Read>(&self, mut reader: R) -> Result<Vec<u8>, SparseError> {
+        let mut raw: Vec<u8> = Vec::new();
+        match self.max_file_size {
+            // Don't read more than needed to know the limit is exceeded
+            Some(max) => reader.take(max.saturating_add(1)).read_to_end(&mut raw)?,
+            None => reader.read_to_end(&mut raw)?,
+        };
+        self.check(SparseLimit::FileSize, raw.len() as u64)?;
+        Ok(raw)
+    }
+}
@@ -0,0 +1,306 @@
+use super::*;
+use path_clean::PathClean;
+use std::fmt::Debug;
+use std::fs;
+use std::path::Path;
+
+#[cfg(feature = "url")]
+use percent_encoding::percent_decode_str;
+#[cfg(feature = "url")]
+use url_inner::Url;
+
+/// # Resolve the documents a [SparseState](crate::SparseState) is referencing
+///
+/// When a pointer targets a document that is not already in the state, the
+/// [SparseState](crate::SparseState) asks its loader for the raw content of
+/// that document, as well as the format it should be parsed with.
+///
+/// Implementing this trait allows to read documents from somewhere else than the
+/// file system (an in-memory map, an archive, etc.).
+pub trait SparseLoader: Debug + SparseSendSync {
+    /// Load the document located at `path`, returning its raw content and its format
+    fn load(&self, path: &Path) -> Result<(Vec<u8>, SparseFileFormat), SparseError>;
+
+    /// Load the document located at `path`, within the `limits` of the state.
+    ///
+    /// By default, the document is loaded then its size is checked. Implementations
+    /// reading large documents should check it before, using
+    /// [SparseLimits::read_to_end](crate::SparseLimits::read_to_end) for instance.
+    fn load_limited(
+        &self,
+        path: &Path,
+        limits: &SparseLimits,
+    ) -> Result<(Vec<u8>, SparseFileFormat), SparseError> {
+        let (raw, format) = self.load(path)?;
+        limits.check(SparseLimit::FileSize, raw.len() as u64)?;
+        Ok((raw, format))
+    }
+
+    /// Load the distant document located at `url`, returning its raw content and its format.
+    ///
+    /// By default, no URL can be fetched. No loader of this crate fetches documents over
+    /// the network, a `http(s)` reference fails with
+    /// [UnsupportedUrl](crate::SparseError::UnsupportedUrl) unless it's mounted in a
+    /// [SparseUrlDirLoader](crate::SparseUrlDirLoader) or handled by a custom loader.
+    #[cfg(feature = "url")]
+    fn load_url(&self, url: &Url) -> Result<(Vec<u8>, SparseFileFormat), SparseError> {
+        Err(SparseError::UnsupportedUrl(url.to_string()))
+    }
+
+    /// Load the distant document located at `url`, within the `limits` of the state.
+    ///
+    /// See [load_limited](crate::SparseLoader::load_limited)
+    #[cfg(feature = "url")]
+    fn load_url_limited(
+        &self,
+        url: &Url,
+        limits: &SparseLimits,
+    ) -> Result<(Vec<u8>, SparseFileFormat), SparseError> {
+        let (raw, format) = self.load_url(url)?;
+        limits.check(SparseLimit::FileSize, raw.len() as u64)?;
+        Ok((raw, format))
+    }
+}
+
+/// # The default [SparseLoader](crate::SparseLoader), reading the documents from the file system
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SparseFileLoader;
+
+impl SparseLoader for SparseFileLoader {
+    fn load(&self, path: &Path) -> Result<(Vec<u8>, SparseFileFormat), SparseError> {
+        self.load_limited(path, &SparseLimits::default())
+    }
+
+    fn load_limited(
+        &self,
+        path: &Path,
+        limits: &SparseLimits,
+    ) -> Result<(Vec<u8>, SparseFileFormat), SparseError> {
+        let file = fs::File::open(path)?;
+        // Don't read a file that is already known to be too big
+        limits.check(SparseLimit::FileSize, file.metadata()?.len())?;
+        let raw = limits.read_to_end(file)?;
+        let format = SparseFileFormat::detect(path, &raw)?;
+        Ok((raw, format))
+    }
+
+    #[cfg(feature = "url")]
+    fn load_url(&self, url: &Url) -> Result<(Vec<u8>, SparseFileFormat), SparseError> {
+        self.load_url_limited(url, &SparseLimits::default())
+    }
+
+    #[cfg(feature = "url")]
+    fn load_url_limited(
+        &self,
+        url: &Url,
+        limits: &SparseLimits,
+    ) -> Result<(Vec<u8>, SparseFileFormat), SparseError> {
+        match url.scheme() {
+            "file" => self.load_limited(
+                url.to_file_path()
+                    .map_err(|_e| SparseError::UnsupportedUrl(url.to_string()))?
+                    .as_path(),
+                limits,
+            ),
+            _ => Err(SparseError::UnsupportedUrl(url.to_string())),
+        }
+    }
+}
+
+/// # A [SparseLoader](crate::SparseLoader) serving documents from a virtual in-memory file system
+///
+/// The files are inserted with their raw text, and optionally their format. Otherwise,
+/// their format is detected like the files read from the disk. The paths can contain
+/// nested directories, so that the relative references resolve exactly as on disk.
+///
+/// The relative paths are rooted at `/`. The root document should then be loaded
+/// from an absolute path, or using `/` as the base path of a
+/// [SparseStateBuilder](crate::SparseStateBuilder).
+#[derive(Debug, Clone, Default)]
+pub struct SparseMemoryFs {
+    files: HashMap<PathBuf, (Vec<u8>, Option<SparseFileFormat>)>,
+}
+
+impl SparseMemoryFs {
+    /// Create a new empty [SparseMemoryFs](crate::SparseMemoryFs)
+    pub fn new() -> Self {
+        SparseMemoryFs::default()
+    }
+
+    /// Get the normalized absolute path of a file
+    fn normalize(path: &Path) -> PathBuf {
+        Path::new("/").join(path).clean()
+    }
+
+    /// Add a file, whose format is detected from its path then its content
+    pub fn with_file<T: Into<Vec<u8>>>(mut self, path: PathBuf, raw: T) -> Self {
+        self.insert(path, raw, None);
+        self
+    }
+
+    /// Add a file in the specified format
+    pub fn with_file_format<T: Into<Vec<u8>>>(
+        mut self,
+        path: PathBuf,
+        raw: T,
+        format: SparseFileFormat,
+    ) -> Self {
+        self.insert(path, raw, Some(format));
+        self
+    }
+
+    /// Add or replace a file. If `format` is `None`, it's detected from its path then its content.
+    pub fn insert<T: Into<Vec<u8>>>(
+        &mut self,
+        path: PathBuf,
+        raw: T,
+        format: Option<SparseFileFormat>,
+    ) {
+        self.files
+            .insert(SparseMemoryFs::normalize(&path), (raw.into(), format));
+    }
+
+    /// Remove a file, returning its raw text if it existed
+    pub fn remove(&mut self, path: &Path) -> Option<Vec<u8>> {
+        self.files
+            .remove(&SparseMemoryFs::normalize(path))
+            .map(|(raw, _format)| raw)
+    }
+
+    /// Check if a file exists
+    pub fn contains(&self, path: &Path) -> bool {
+        self.files.contains_key(&SparseMemoryFs::normalize(path))
+    }
+
+    /// List the paths of the files in the directory `dir` and its children
+    pub fn files_in(&self, dir: &Path) -> Vec<PathBuf> {
+        let dir = SparseMemoryFs::normalize(dir);
+        let mut res: Vec<PathBuf> = self
+            .files
+            .keys()
+            .filter(|path| path.starts_with(&dir))
+            .cloned()
+            .collect();
+        res.sort();
+        res
+    }
+}
+
+impl SparseLoader for SparseMemoryFs {
+    fn load(&self, path: &Path) -> Result<(Vec<u8>, SparseFileFormat), SparseError> {
+        self.load_limited(path, &SparseLimits::default())
+    }
+
+    fn load_limited(
+        &self,
+        path: &Path,
+        limits: &SparseLimits,
+    ) -> Result<(Vec<u8>, SparseFileFormat), SparseError> {
+        let (raw, format) = self
+            .files
+            .get(&SparseMemoryFs::normalize(path))
+            .ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!("`{}` is not in the memory file system", path.display()),
+                )
+            })?;
+        limits.check(SparseLimit::FileSize, raw.len() as u64)?;
+        let format = match format {
+            Some(format) => *format,
+            None => SparseFileFormat::detect(path, raw)?,
+        };
+        Ok((raw.clone(), format))
+    }
+}
+
+/// # A [SparseLoader](crate::SparseLoader) serving URLs from local directories
+///
+/// Each base URL is mounted on a directory. A distant document is then read from
+/// the directory whose base URL prefixes the document's URL, segment by segment,
+/// the rest of its path being percent-decoded. It's useful to work offline with a
+/// mirror of distant documents, or to back tests with fixtures.
+///
+/// The URLs that aren't under any base URL aren't fetched, they fail with
+/// [UnsupportedUrl](crate::SparseError::UnsupportedUrl).
+///
+/// Local files are read from the file system, like [SparseFileLoader](crate::SparseFileLoader).
+#[cfg(feature = "url")]
+#[derive(Debug, Clone, Default)]
+pub struct SparseUrlDirLoader {
+    mounts: Vec<(Url, PathBuf)>,
+}
+
+#[cfg(feature = "url")]
+impl SparseUrlDirLoader {
+    /// Create a new [SparseUrlDirLoader](crate::SparseUrlDirLoader) without any mount point
+    pub fn new() -> Self {
+        SparseUrlDirLoader::default()
+    }
+
+    /// Serve the URLs under `base` from the directory `dir`.
+    ///
+    /// The base URL is a directory, `https://example.com/schemas` serving
+    /// `https://example.com/schemas/pet.yaml` but not `https://example.com/schemas-old/pet.yaml`.
+    pub fn mount(mut self, mut base: Url, dir: PathBuf) -> Self {
+        if !base.path().ends_with('/') {
+            let path = format!("{}/", base.path());
+            base.set_path(&path);
+        }
+        self.mounts.push((base, dir));
+        self
+    }
+
+    /// The file serving `url` in the directory `dir` mounted on `base`, if `url` is under `base`
+    fn mounted_path(base: &Url, dir: &Path, url: &Url) -> Option<PathBuf> {
+        if url.scheme() != base.scheme()
+            || url.host() != base.host()
+            || url.port_or_known_default() != base.port_or_known_default()
+        {
+            return None;
+        }
+        let rel = url.path().strip_prefix(base.path())?;
+        let mut res = dir.to_path_buf();
+        for segment in rel.split('/') {
+            let segment = percent_decode_str(segment).decode_utf8().ok()?;
+            // A decoded segment mustn't escape the directory
+            if segment == ".." || segment.contains(['/', '\\']) {
+                return None;
+            }
+            res.push(segment.as_ref());
+        }
+        Some(res)
+    }
+}
+
+#[cfg(feature = "url")]
+impl SparseLoader for SparseUrlDirLoader {
+    fn load(&self, path: &Path) -> Result<(Vec<u8>, SparseFileFormat), SparseError> {
+        SparseFileLoader.load(path)
+    }
+
+    fn load_limited(
+        &self,
+        path: &Path,
+        limits: &SparseLimits,
+    ) -> Result<(Vec<u8>, SparseFileFormat), SparseError> {
+        SparseFileLoader.load_limited(path, limits)
+    }
+
+    fn load_url(&self, url: &Url) -> Result<(Vec<u8>, SparseFileFormat), SparseError> {
+        self.load_url_limited(url, &SparseLimits::default())
+    }
+
+    fn load_url_limited(
+        &self,
+        url: &Url,
+        limits: &SparseLimits,
+    ) -> Result<(Vec<u8>, SparseFileFormat), SparseError> {
+        for (base, dir) in self.mounts.iter() {
+            if let Some(path) = SparseUrlDirLoader::mounted_path(base, dir, url) {
+                return SparseFileLoader.load_limited(path.as_path(), limits);
+            }
+        }
+        SparseFileLoader.load_url_limited(url, limits)
+    }
+}
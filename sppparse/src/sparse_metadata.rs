@@ -1,7 +1,11 @@
 use super::*;
+use crate::sparse_document_id::SparseDocumentId;
 use path_absolutize::*;
 use path_clean::PathClean;
 
+#[cfg(feature = "url")]
+use url_inner::Url;
+
 /// # Metadata about the pointer
 ///
 /// [SparseMetadata](SparseMetadata) store crucial information like the file
@@ -21,6 +25,11 @@ pub struct SparseMetadata {
     #[serde(skip)]
     #[getset(get = "pub")]
     pfile_path: PathBuf,
+    /// The parent file URL, if the pointer points to a distant document
+    #[cfg(feature = "url")]
+    #[serde(skip)]
+    #[getset(get = "pub")]
+    pfile_url: Option<Url>,
     /// The pointer string, as it is set in the original Value
     #[serde(rename = "$ref")]
     #[getset(get = "pub", get_mut = "pub")]
@@ -28,7 +37,10 @@ pub struct SparseMetadata {
     /// The parsed pointer, if any
     #[serde(skip)]
     #[getset(get = "pub")]
-    pointer: String,
+    pointer: JsonPointer,
+    /// Where the pointer lives in the document it originates from, if known
+    #[serde(skip)]
+    location: Option<Box<SparseLocation>>,
 }
 
 impl SparseMetadata {
@@ -46,56 +58,150 @@ impl SparseMetadata {
         }
     }
 
-    /// Parse the raw pointer
-    fn parse_pointer(raw_pointer: &str, base_path: PathBuf) -> (PathBuf, String) {
-        let mut raw_pointer: String = raw_pointer.to_string();
-        let hash_pos: Option<usize> = raw_pointer.find('#');
-        let pfile: Option<PathBuf>;
-        let mut pointer_path_str: String;
-        match hash_pos {
-            Some(pos) => match pos {
-                0 => {
-                    pfile = None;
-                    pointer_path_str = (&raw_pointer[1..raw_pointer.len()]).to_string();
-                }
-                _ => {
-                    let old_len = raw_pointer.len();
-                    pointer_path_str =
-                        (&(raw_pointer.split_off(pos))[1..(old_len - pos)]).to_string();
-                    pfile = Some(PathBuf::from(raw_pointer.as_str()));
-                }
-            },
-            None => {
-                pfile = None;
-                pointer_path_str = raw_pointer;
-            }
+    /// Split the raw pointer between the file part, if any, and the pointer part
+    pub(crate) fn split_pointer(raw_pointer: &str) -> (Option<&str>, String) {
+        let (pfile, mut pointer_path_str): (Option<&str>, String) = match raw_pointer.find('#') {
+            Some(0) => (None, raw_pointer[1..].to_string()),
+            Some(pos) => (
+                Some(&raw_pointer[..pos]),
+                raw_pointer[pos + 1..].to_string(),
+            ),
+            None => (None, raw_pointer.to_string()),
         };
         if !pointer_path_str.is_empty() && pointer_path_str.as_bytes()[0] != b'/' {
             pointer_path_str.insert(0, '/');
-        } else if pointer_path_str.is_empty() {
-            pointer_path_str.push('/');
         }
+        (pfile, pointer_path_str)
+    }
+
+    /// Parse the raw pointer
+    fn parse_pointer(
+        raw_pointer: &str,
+        base_path: PathBuf,
+    ) -> Result<(PathBuf, JsonPointer), SparseError> {
+        let (pfile, pointer_path_str) = SparseMetadata::split_pointer(raw_pointer);
 
         let pfile_res = match (pfile, base_path) {
             (Some(pfile_inner), mut path_inner) => {
                 path_inner.pop();
                 path_inner.push(pfile_inner);
-                path_inner
+                // Resolve the `..` lexically, so that a document has a single path in the state
+                path_inner.clean()
             }
             (None, path_inner) => path_inner,
         };
-        (pfile_res, pointer_path_str)
+        let pointer = match raw_pointer.contains('#') {
+            // The pointer is a URI fragment, it may be percent-encoded
+            true => JsonPointer::parse_uri_fragment(&pointer_path_str)?,
+            false => JsonPointer::parse(&pointer_path_str)?,
+        };
+        Ok((pfile_res, pointer))
+    }
+
+    /// Resolve the URL of the document a raw pointer is referencing, if any.
+    ///
+    /// The file part of the pointer is either an absolute URL or, if the pointer
+    /// originates from a distant document, a URL relative to that document.
+    #[cfg(feature = "url")]
+    fn parse_pointer_url(raw_pointer: &str, base_url: Option<&Url>) -> Option<Url> {
+        match (SparseMetadata::split_pointer(raw_pointer).0, base_url) {
+            (Some(pfile), base_url) => match Url::parse(pfile) {
+                // A single letter scheme is a Windows drive, not a URL
+                Ok(url) if url.scheme().len() > 1 => Some(url),
+                _ => base_url.and_then(|base| base.join(pfile).ok()),
+            },
+            (None, base_url) => base_url.cloned(),
+        }
     }
 
     /// Create a new [SparseMetadata](SparseMetadata)
-    pub fn new(raw_ptr: String, path: PathBuf) -> Self {
-        let (pfile_path, pointer) = SparseMetadata::parse_pointer(&raw_ptr, path);
+    pub fn new(raw_ptr: String, path: PathBuf) -> Result<Self, SparseError> {
+        let (pfile_path, pointer) = SparseMetadata::parse_pointer(&raw_ptr, path)?;
         let version = 0;
-        SparseMetadata {
+        Ok(SparseMetadata {
             raw_pointer: raw_ptr,
             pointer,
             pfile_path,
+            #[cfg(feature = "url")]
+            pfile_url: None,
             version,
+            location: None,
+        })
+    }
+
+    /// Create a new [SparseMetadata](SparseMetadata), resolving relative
+    /// URLs against `base_url`
+    #[cfg(feature = "url")]
+    pub fn new_with_url(
+        raw_ptr: String,
+        path: PathBuf,
+        base_url: Option<&Url>,
+    ) -> Result<Self, SparseError> {
+        let pfile_url = SparseMetadata::parse_pointer_url(&raw_ptr, base_url);
+        let mut res = SparseMetadata::new(raw_ptr, path.clone())?;
+        if pfile_url.is_some() {
+            // The local path is meaningless for distant documents, keep the referencing one.
+            res.pfile_path = path;
+        }
+        res.pfile_url = pfile_url;
+        Ok(res)
+    }
+
+    /// Create a new [SparseMetadata](SparseMetadata) for a pointer located
+    /// in the document described by `parent`
+    #[cfg(feature = "url")]
+    pub fn new_from_parent(raw_ptr: String, parent: &SparseMetadata) -> Result<Self, SparseError> {
+        SparseMetadata::new_with_url(
+            raw_ptr,
+            parent.pfile_path().clone(),
+            parent.pfile_url().as_ref(),
+        )
+    }
+
+    /// Create a new [SparseMetadata](SparseMetadata) for a pointer located
+    /// in the document described by `parent`
+    #[cfg(not(feature = "url"))]
+    pub fn new_from_parent(raw_ptr: String, parent: &SparseMetadata) -> Result<Self, SparseError> {
+        SparseMetadata::new(raw_ptr, parent.pfile_path().clone())
+    }
+
+    /// Where the pointer lives in the document it originates from, if known
+    pub fn location(&self) -> Option<&SparseLocation> {
+        self.location.as_deref()
+    }
+
+    /// Set where the pointer lives in the document it originates from
+    pub(crate) fn set_location(&mut self, location: SparseLocation) {
+        self.location = Some(Box::new(location));
+    }
+
+    /// Attach the location of the pointer to an error that occurred while resolving it
+    pub(crate) fn locate_err(&self, err: SparseError) -> SparseError {
+        match &self.location {
+            Some(location) => location.wrap_err(err),
+            None => err,
+        }
+    }
+
+    /// Deserialize a value of the document this pointer is pointing to, reporting where it failed
+    pub(crate) fn deserialize<T: DeserializeOwned>(&self, val: &Value) -> Result<T, SparseError> {
+        serde_path_to_error::deserialize(val).map_err(|err| SparseError::Deserialize {
+            file: SparseDocumentId::new(self).to_string(),
+            pointer: self.pointer().to_string(),
+            path: err.path().to_string(),
+            source: err.into_inner(),
+        })
+    }
+
+    /// Like `deserialize`, but the value may itself be a pointer
+    pub(crate) fn deserialize_pointed<S: DeserializeOwned>(
+        &self,
+        val: &Value,
+    ) -> Result<SparsePointedValue<S>, SparseError> {
+        match SparsePointedValue::<S>::deserialize(val) {
+            Ok(res) => Ok(res),
+            // The untagged enum hides the cause, deserialize `S` to find where it failed
+            Err(_err) => self.deserialize::<S>(val).map(SparsePointedValue::Obj),
         }
     }
 }
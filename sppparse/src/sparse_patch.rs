@@ -0,0 +1,331 @@
+use super::*;
+use std::ops::Range;
+
+#[cfg(feature = "json5")]
+use crate::sparse_span::json_value_len;
+
+/// # Rewrite a document by patching its original text
+///
+/// The values that changed since the original text was parsed are replaced in place,
+/// so that the comments, the formatting and the order of the rest of the document
+/// stay byte-identical.
+///
+/// The changes of scalar values can be patched. In `YAML`, keys can also be added to or
+/// removed from block mappings, and items appended to or truncated from block sequences.
+/// The other changes, like changing the type of a collection, require the document to be
+/// serialized again.
+#[derive(Debug)]
+struct SparsePatcher<'a> {
+    /// The original text of the document
+    raw: &'a str,
+    /// The format of the original text
+    format: SparseFileFormat,
+    /// The position of every value of the original text
+    spans: &'a HashMap<JsonPointer, SparseSpan>,
+    /// The ranges of the original text to replace, with their replacement
+    edits: Vec<(Range<usize>, String)>,
+}
+
+impl<'a> SparsePatcher<'a> {
+    /// Record the edits turning `old` into `new`, returning `None` if they can't be patched
+    fn diff(&mut self, old: &Value, new: &Value, pointer: &mut JsonPointer) -> Option<()> {
+        match (old, new) {
+            (old, new) if old == new => Some(()),
+            (Value::Object(old_map), Value::Object(new_map)) => {
+                let removed: Vec<usize> = old_map
+                    .keys()
+                    .enumerate()
+                    .filter(|(_idx, key)| !new_map.contains_key(*key))
+                    .map(|(idx, _key)| idx)
+                    .collect();
+                let added: Vec<Value> = new_map
+                    .iter()
+                    .filter(|(key, _val)| !old_map.contains_key(*key))
+                    .map(|(key, val)| {
+                        let mut entry = serde_json::Map::new();
+                        entry.insert(key.clone(), val.clone());
+                        Value::Object(entry)
+                    })
+                    .collect();
+                for (key, old_val) in old_map.iter() {
+                    if let Some(new_val) = new_map.get(key) {
+                        pointer.push(key.clone());
+                        self.diff(old_val, new_val, pointer)?;
+                        pointer.pop();
+                    }
+                }
+                // The entries of the children are appended first, they're before in the text
+                if !removed.is_empty() || !added.is_empty() {
+                    self.resize_block(pointer, old_map.len(), &removed, &added)?;
+                }
+                Some(())
+            }
+            (Value::Array(old_list), Value::Array(new_list)) => {
+                for (idx, (old_val, new_val)) in old_list.iter().zip(new_list.iter()).enumerate() {
+                    pointer.push(idx.to_string());
+                    self.diff(old_val, new_val, pointer)?;
+                    pointer.pop();
+                }
+                let common = old_list.len().min(new_list.len());
+                if old_list.len() != new_list.len() {
+                    let removed: Vec<usize> = (common..old_list.len()).collect();
+                    let added: Vec<Value> = new_list[common..]
+                        .iter()
+                        .map(|val| Value::Array(vec![val.clone()]))
+                        .collect();
+                    self.resize_block(pointer, old_list.len(), &removed, &added)?;
+                }
+                Some(())
+            }
+            (Value::Object(_), _) | (Value::Array(_), _) => None,
+            (_, Value::Object(_)) | (_, Value::Array(_)) => None,
+            (old, new) => {
+                let start = self.spans.get(pointer)?.offset();
+                let end = start + self.scalar_len(start, old)?;
+                let replacement = self.scalar(new, &self.raw[start..end])?;
+                self.edits.push((start..end, replacement));
+                Some(())
+            }
+        }
+    }
+
+    /// Get the entries of the `YAML` block collection at `pointer` in the original text.
+    ///
+    /// Returns the indentation of the entries, then the offset of the start of each entry
+    /// with the offset of the end of its last line.
+    fn yaml_block(&self, pointer: &JsonPointer) -> Option<(usize, Vec<(usize, usize)>)> {
+        // A block mapping starts at the colon of its first key, a block sequence at its first dash
+        let mark = self.spans.get(pointer)?.offset();
+        let line_start = self.raw[..mark].rfind('\n').map_or(0, |pos| pos + 1);
+        let key = self.raw[line_start..mark].trim_start_matches([' ', '-']);
+        let start = mark - key.len();
+        let sequence = match (self.raw[mark..].bytes().next()?, key.is_empty()) {
+            (b'-', true) => true,
+            (b':', false) => false,
+            // Flow collections and explicit keys are left to the serializer
+            _ => return None,
+        };
+        // The first entry may follow the dash of the item of a parent sequence
+        if !self.raw[line_start..start]
+            .bytes()
+            .all(|x| x == b' ' || x == b'-')
+        {
+            return None;
+        }
+        let indent = start - line_start;
+        let line_end = |pos: usize| {
+            self.raw[pos..]
+                .find('\n')
+                .map_or(self.raw.len(), |x| pos + x + 1)
+        };
+        let mut entries: Vec<(usize, usize)> = vec![(start, line_end(start))];
+        let mut pos = line_end(start);
+        while pos < self.raw.len() {
+            let line = &self.raw[pos..line_end(pos)];
+            let content = line.trim_start_matches(' ');
+            let line_indent = line.len() - content.len();
+            if content.trim().is_empty() || content.starts_with('#') {
+                pos = line_end(pos);
+                continue;
+            }
+            if line_indent < indent || content.starts_with("---") || content.starts_with("...") {
+                break;
+            }
+            let dash = content.starts_with("- ") || content.trim_end() == "-";
+            match (line_indent == indent, sequence, dash) {
+                // A sequence ends with its last dash at its indentation
+                (true, true, false) => break,
+                // The items of a sequence can be at the indentation of the key owning it
+                (true, true, true) | (true, false, false) => {
+                    entries.push((pos + line_indent, line_end(pos)))
+                }
+                _ => entries.last_mut()?.1 = line_end(pos),
+            }
+            pos = line_end(pos);
+        }
+        Some((indent, entries))
+    }
+
+    /// Remove the entries at the indexes `removed` of the `YAML` block collection at `pointer`,
+    /// and append the entries of the collections `added`
+    fn resize_block(
+        &mut self,
+        pointer: &JsonPointer,
+        len: usize,
+        removed: &[usize],
+        added: &[Value],
+    ) -> Option<()> {
+        if self.format != SparseFileFormat::Yaml || (removed.len() == len && added.is_empty()) {
+            return None;
+        }
+        let (indent, entries) = self.yaml_block(pointer)?;
+        if entries.len() != len {
+            return None;
+        }
+        for idx in removed.iter() {
+            let (start, end) = entries[*idx];
+            let line_start = self.raw[..start].rfind('\n').map_or(0, |pos| pos + 1);
+            let range = match self.raw[line_start..start].trim_start_matches(' ') {
+                // Removing the whole lines of the entry keeps the rest of the document aligned
+                "" => line_start..end,
+                // The first entry follows the dash of a parent sequence, the next one takes its place
+                _ => start..entries.get(*idx + 1)?.0,
+            };
+            self.edits.push((range, String::new()));
+        }
+        let (_start, end) = *entries.last()?;
+        // The last line may lack its newline, unless a child already appended entries after it
+        let mut insertion = match self.raw[..end].ends_with('\n')
+            || self.edits.iter().any(|(range, _val)| *range == (end..end))
+        {
+            true => String::new(),
+            false => String::from("\n"),
+        };
+        for val in added.iter() {
+            let res = serde_yaml::to_string(val).ok()?;
+            for line in res.strip_prefix("---\n").unwrap_or(&res).lines() {
+                insertion.push_str(&" ".repeat(indent));
+                insertion.push_str(line);
+                insertion.push('\n');
+            }
+        }
+        if !added.is_empty() {
+            self.edits.push((end..end, insertion));
+        }
+        Some(())
+    }
+
+    /// Get the length of the scalar `old` starting at `start` in the original text
+    fn scalar_len(&self, start: usize, old: &Value) -> Option<usize> {
+        let text = self.raw.get(start..)?;
+        match self.format {
+            SparseFileFormat::Json(_) => {
+                let mut stream = serde_json::Deserializer::from_str(text).into_iter::<Value>();
+                stream.next()?.ok()?;
+                Some(stream.byte_offset())
+            }
+            SparseFileFormat::Yaml => SparsePatcher::yaml_scalar_len(text, old),
+            #[cfg(feature = "toml")]
+            SparseFileFormat::Toml => None,
+            // The end found by the lenient scanner is checked against the old value
+            #[cfg(feature = "json5")]
+            SparseFileFormat::Json5 => {
+                let len = json_value_len(text.as_bytes())?;
+                match json5_inner::from_str::<Value>(text.get(..len)?) {
+                    Ok(val) if &val == old => Some(len),
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    /// Get the length of the single line `YAML` scalar `old` at the start of `text`
+    fn yaml_scalar_len(text: &str, old: &Value) -> Option<usize> {
+        let bytes = text.as_bytes();
+        match *bytes.first()? {
+            quote @ b'\'' | quote @ b'"' => {
+                let mut idx = 1;
+                while idx < bytes.len() {
+                    match bytes[idx] {
+                        b'\n' => return None,
+                        b'\\' if quote == b'"' => idx += 1,
+                        b'\'' if quote == b'\'' && bytes.get(idx + 1) == Some(&b'\'') => idx += 1,
+                        x if x == quote => return Some(idx + 1),
+                        _ => (),
+                    }
+                    idx += 1;
+                }
+                None
+            }
+            // Block scalars, anchors, aliases and tags are left to the serializer
+            b'|' | b'>' | b'&' | b'*' | b'!' => None,
+            _ => {
+                let line = text.split('\n').next()?;
+                let line = match line.find(" #") {
+                    Some(pos) => &line[..pos],
+                    None => line,
+                };
+                // Inside a flow collection, the scalar stops at the next indicator
+                let flow = match line.find([',', ']', '}']) {
+                    Some(pos) => &line[..pos],
+                    None => line,
+                };
+                [line.trim_end(), flow.trim_end()]
+                    .iter()
+                    .find(|x| serde_yaml::from_str::<Value>(x).ok().as_ref() == Some(old))
+                    .map(|x| x.len())
+            }
+        }
+    }
+
+    /// Serialize a scalar in the format of the original text, keeping the quoting style of `old`
+    fn scalar(&self, val: &Value, old: &str) -> Option<String> {
+        let res = match (self.format, val, old.as_bytes().first()) {
+            #[cfg(feature = "json5")]
+            (SparseFileFormat::Json5, Value::String(x), Some(b'\'')) => {
+                let res = serde_json::to_string(x).ok()?;
+                format!(
+                    "'{}'",
+                    res[1..res.len() - 1]
+                        .replace("\\\"", "\"")
+                        .replace('\'', "\\'")
+                )
+            }
+            #[cfg(feature = "json5")]
+            (SparseFileFormat::Json5, _, _) => serde_json::to_string(val).ok()?,
+            (SparseFileFormat::Json(_), _, _)
+            | (SparseFileFormat::Yaml, Value::String(_), Some(b'"')) => {
+                serde_json::to_string(val).ok()?
+            }
+            (SparseFileFormat::Yaml, Value::String(x), Some(b'\'')) => {
+                format!("'{}'", x.replace('\'', "''"))
+            }
+            (SparseFileFormat::Yaml, _, _) => {
+                let res = serde_yaml::to_string(val).ok()?;
+                let res = res.strip_prefix("---").unwrap_or(&res).trim();
+                res.to_string()
+            }
+            #[cfg(feature = "toml")]
+            (SparseFileFormat::Toml, _, _) => return None,
+        };
+        match res.contains('\n') {
+            true => None,
+            false => Some(res),
+        }
+    }
+
+    /// Apply the recorded edits to the original text, returning `None` if they overlap
+    fn apply(mut self) -> Option<String> {
+        let mut res = String::with_capacity(self.raw.len());
+        let mut last: usize = 0;
+        self.edits.sort_by_key(|(range, _)| range.start);
+        for (range, replacement) in self.edits.iter() {
+            res.push_str(self.raw.get(last..range.start)?);
+            res.push_str(replacement);
+            last = range.end;
+        }
+        res.push_str(&self.raw[last..]);
+        Some(res)
+    }
+}
+
+/// Patch the original text of a document so that it matches `val`.
+///
+/// Returns `None` if the changes can't be applied as patches, in which case the document
+/// should be serialized again.
+pub(crate) fn patch(raw: &str, format: SparseFileFormat, val: &Value) -> Option<String> {
+    let original = SparseState::parse_raw(raw.as_bytes(), format).ok()?;
+    let mut patcher = SparsePatcher {
+        raw,
+        format,
+        spans: original.spans(),
+        edits: Vec::new(),
+    };
+    patcher.diff(original.val(), val, &mut JsonPointer::new())?;
+    let res = patcher.apply()?;
+    // Make sure the patched text means what it's supposed to
+    match SparseState::parse_raw(res.as_bytes(), format) {
+        Ok(patched) if patched.val() == val => Some(res),
+        _ => None,
+    }
+}
@@ -4,12 +4,13 @@ use super::*;
 ///
 /// The [SparsePointedValue](SparsePointedValue) allows to switch between a raw, unparsed pointer
 /// to a parsed pointer resolved at initialization.
-#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Default)]
 #[serde(untagged)]
 pub enum SparsePointedValue<S> {
     RefRaw(Box<SparseRefRaw<S>>),
     Obj(S),
     Ref(SparseRef<S>),
+    #[default]
     Null,
 }
 
@@ -23,7 +24,7 @@ where
         metadata: &SparseMetadata,
         depth: u32,
     ) -> Result<(), SparseError> {
-        self.check_depth(depth)?;
+        self.check_depth(state, depth)?;
         match self {
             SparsePointedValue::Null => self.self_reset(state, metadata, depth)?,
             _ => {
@@ -46,7 +47,7 @@ where
         metadata: &SparseMetadata,
         depth: u32,
     ) -> Result<(), SparseError> {
-        self.check_depth(depth)?;
+        self.check_depth(state, depth)?;
         let vcheck = self.check_version(state);
         match vcheck {
             Ok(()) => (),
@@ -62,12 +63,6 @@ where
     }
 }
 
-impl<S> std::default::Default for SparsePointedValue<S> {
-    fn default() -> Self {
-        SparsePointedValue::Null
-    }
-}
-
 impl<S> SparsePointedValue<S> {
     pub fn default_boxed() -> Box<Self> {
         Box::new(SparsePointedValue::<S>::default())
@@ -101,13 +96,11 @@ where
 
     fn get_mut<'a>(
         &'a mut self,
-        state_cell: Rc<RefCell<SparseState>>,
+        state_cell: SparseStateCell,
         metadata: Option<&'a SparseMetadata>,
     ) -> Result<SparseValueMut<'a, S>, SparseError> {
         {
-            let state = state_cell
-                .try_borrow()
-                .map_err(|_e| SparseError::StateAlreadyBorrowed)?;
+            let state = state_cell.try_borrow_state()?;
             self.check_version(&state)?;
         }
         match self {
@@ -118,13 +111,13 @@ where
         }
     }
 
-    fn self_reset<'a>(
+    fn self_reset(
         &mut self,
         state: &mut SparseState,
-        metadata: &'a SparseMetadata,
+        metadata: &SparseMetadata,
         depth: u32,
     ) -> Result<(), SparseError> {
-        self.check_depth(depth)?;
+        self.check_depth(state, depth)?;
         match self {
             SparsePointedValue::Ref(x) => Ok(x.self_reset(state, metadata, depth + 1)?),
             SparsePointedValue::Obj(_x) => Ok(()),
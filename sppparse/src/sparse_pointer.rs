@@ -7,7 +7,7 @@ pub trait SparsePointer<S: DeserializeOwned + Serialize + SparsableTrait> {
     /// Get the inner value of the pointer (mutable)
     fn get_mut(
         &mut self,
-        state_cell: Rc<RefCell<SparseState>>,
+        state_cell: SparseStateCell,
     ) -> Result<SparseValueMut<'_, S>, SparseError>;
     /// Check if the inner value is outdated
     fn check_version(&self, state: &SparseState) -> Result<(), SparseError>;
@@ -30,7 +30,7 @@ pub trait SparsePointerRaw<S: DeserializeOwned + Serialize + SparsableTrait> {
     /// Get the inner value of the pointer (mutable)
     fn get_mut<'a>(
         &'a mut self,
-        state_cell: Rc<RefCell<SparseState>>,
+        state_cell: SparseStateCell,
         metadata: Option<&'a SparseMetadata>,
     ) -> Result<SparseValueMut<'a, S>, SparseError>;
     /// Check if the inner value is outdated
@@ -0,0 +1,165 @@
+use super::*;
+use path_absolutize::*;
+use path_clean::PathClean;
+use std::path::Path;
+
+#[cfg(feature = "url")]
+use url_inner::Url;
+
+/// # Restrict the documents a [SparseState](crate::SparseState) can fetch
+///
+/// When loading untrusted documents, their references can point anywhere on the file
+/// system (`../../../etc/passwd#/`, `/etc/passwd#/`) or the network. The policy is
+/// checked before fetching any document that is not already in the state, failing
+/// with [Forbidden](crate::SparseError::Forbidden) on violations.
+///
+/// The default policy doesn't restrict anything.
+#[derive(Debug, Clone, Default, Getters, CopyGetters)]
+pub struct SparsePolicy {
+    /// The directories the files must be in. Any file is allowed if empty.
+    #[getset(get = "pub")]
+    allowed_roots: Vec<PathBuf>,
+    /// True if the references can't use absolute paths
+    #[getset(get_copy = "pub")]
+    absolute_paths_denied: bool,
+    /// True if the files must still be in an allowed root once their symlinks are resolved
+    #[getset(get_copy = "pub")]
+    symlink_escapes_denied: bool,
+    /// The URL schemes that can be fetched. Any scheme is allowed if `None`.
+    #[cfg(feature = "url")]
+    #[getset(get = "pub")]
+    allowed_schemes: Option<Vec<String>>,
+}
+
+impl SparsePolicy {
+    /// Create a new [SparsePolicy](crate::SparsePolicy) without any restriction
+    pub fn new() -> Self {
+        SparsePolicy::default()
+    }
+
+    /// Create a new [SparsePolicy](crate::SparsePolicy) confining the documents to `root`.
+    ///
+    /// Absolute paths, symlinks escaping `root` and URLs are denied.
+    pub fn sandboxed(root: PathBuf) -> Self {
+        let res = SparsePolicy::new()
+            .allow_root(root)
+            .deny_absolute_paths(true)
+            .deny_symlink_escapes(true);
+        #[cfg(feature = "url")]
+        let res = SparsePolicy {
+            allowed_schemes: Some(Vec::new()),
+            ..res
+        };
+        res
+    }
+
+    /// Allow the files in the directory `root` and its children
+    pub fn allow_root(mut self, root: PathBuf) -> Self {
+        self.allowed_roots.push(root);
+        self
+    }
+
+    /// Deny, or allow, the references using absolute paths, `file` URLs included
+    pub fn deny_absolute_paths(mut self, deny: bool) -> Self {
+        self.absolute_paths_denied = deny;
+        self
+    }
+
+    /// Deny, or allow, the files whose symlinks resolve outside of the allowed roots
+    pub fn deny_symlink_escapes(mut self, deny: bool) -> Self {
+        self.symlink_escapes_denied = deny;
+        self
+    }
+
+    /// Allow fetching the URLs using `scheme`. Once called, the other schemes are denied.
+    #[cfg(feature = "url")]
+    pub fn allow_scheme(mut self, scheme: &str) -> Self {
+        self.allowed_schemes
+            .get_or_insert_with(Vec::new)
+            .push(scheme.to_ascii_lowercase());
+        self
+    }
+
+    /// Build the error of a violation of the policy
+    fn forbidden(target: &str, reason: &str) -> SparseError {
+        SparseError::Forbidden {
+            target: target.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
+    /// Check the path of the file a reference is pointing to, as written in the reference
+    pub(crate) fn check_ref_path(&self, path: &str) -> Result<(), SparseError> {
+        if self.absolute_paths_denied && Path::new(path).is_absolute() {
+            return Err(SparsePolicy::forbidden(path, "absolute paths are denied"));
+        }
+        Ok(())
+    }
+
+    /// Check a file can be fetched, before it's added to the state
+    pub(crate) fn check_path(&self, path: &Path) -> Result<(), SparseError> {
+        if self.allowed_roots.is_empty() {
+            return Ok(());
+        }
+        let target = path.to_string_lossy();
+        let roots = self
+            .allowed_roots
+            .iter()
+            .map(|root| Ok(root.absolutize()?.to_path_buf().clean()))
+            .collect::<Result<Vec<PathBuf>, SparseError>>()?;
+        let path = path.absolutize()?.to_path_buf().clean();
+        if !roots.iter().any(|root| path.starts_with(root)) {
+            return Err(SparsePolicy::forbidden(
+                &target,
+                "it's outside of the allowed roots",
+            ));
+        }
+        if !self.symlink_escapes_denied {
+            return Ok(());
+        }
+        // A file that doesn't exist fails to load anyway
+        let path = match path.canonicalize() {
+            Ok(path) => path,
+            Err(_) => return Ok(()),
+        };
+        match roots
+            .iter()
+            .filter_map(|root| root.canonicalize().ok())
+            .any(|root| path.starts_with(root))
+        {
+            true => Ok(()),
+            false => Err(SparsePolicy::forbidden(
+                &target,
+                "it resolves outside of the allowed roots",
+            )),
+        }
+    }
+
+    /// Check a distant document can be fetched, before it's added to the state
+    #[cfg(feature = "url")]
+    pub(crate) fn check_url(&self, url: &Url) -> Result<(), SparseError> {
+        if let Some(schemes) = &self.allowed_schemes {
+            if !schemes.iter().any(|scheme| scheme == url.scheme()) {
+                return Err(SparsePolicy::forbidden(
+                    url.as_str(),
+                    "its scheme is denied",
+                ));
+            }
+        }
+        if url.scheme() != "file" {
+            return Ok(());
+        }
+        // A `file` URL is an absolute path
+        if self.absolute_paths_denied {
+            return Err(SparsePolicy::forbidden(
+                url.as_str(),
+                "absolute paths are denied",
+            ));
+        }
+        // The local files are still confined to the allowed roots
+        match url.to_file_path() {
+            Ok(path) => self.check_path(&path),
+            Err(_) => Ok(()),
+        }
+    }
+}
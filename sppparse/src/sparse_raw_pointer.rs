@@ -0,0 +1,91 @@
+use super::*;
+use serde::de::{Deserializer, Visitor};
+use serde::Serializer;
+use std::fmt;
+
+/// # The raw pointer of a reference, as it is deserialized
+///
+/// When it's deserialized from a document of the [SparseState](crate::SparseState), its text
+/// is borrowed from the document. The address of that text is kept, allowing the state to find
+/// where the reference lives in the document without searching it.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SparseRawPointer {
+    /// The raw pointer
+    raw: String,
+    /// The address of the text in the document it was deserialized from, until located
+    origin: Option<usize>,
+    /// Where the reference lives in the document it was deserialized from, once located
+    location: Option<JsonPointer>,
+}
+
+impl SparseRawPointer {
+    /// Create a new [SparseRawPointer](SparseRawPointer), not originating from a document
+    pub(crate) fn new(raw: String) -> Self {
+        SparseRawPointer {
+            raw,
+            origin: None,
+            location: None,
+        }
+    }
+
+    /// The raw pointer
+    pub(crate) fn raw(&self) -> &String {
+        &self.raw
+    }
+
+    /// Where the reference lives in its document, if it could be located
+    pub(crate) fn location(&self) -> Option<&JsonPointer> {
+        self.location.as_ref()
+    }
+
+    /// Locate the reference in the document it was deserialized from, if not already done
+    pub(crate) fn locate(&mut self, state_file: &SparseStateFile) {
+        if let Some(origin) = self.origin.take() {
+            self.location = state_file.locate_str(origin, &self.raw);
+        }
+    }
+}
+
+impl PartialEq for SparseRawPointer {
+    fn eq(&self, other: &Self) -> bool {
+        self.raw == other.raw
+    }
+}
+
+impl Serialize for SparseRawPointer {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.raw)
+    }
+}
+
+impl<'de> Deserialize<'de> for SparseRawPointer {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_string(SparseRawPointerVisitor)
+    }
+}
+
+struct SparseRawPointerVisitor;
+
+impl<'de> Visitor<'de> for SparseRawPointerVisitor {
+    type Value = SparseRawPointer;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a pointer string")
+    }
+
+    fn visit_borrowed_str<E>(self, v: &'de str) -> Result<Self::Value, E> {
+        Ok(SparseRawPointer {
+            raw: v.to_string(),
+            origin: Some(v.as_ptr() as usize),
+            location: None,
+        })
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(SparseRawPointer::new(v.to_string()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Self::Value, E> {
+        Ok(SparseRawPointer::new(v))
+    }
+}
@@ -1,4 +1,5 @@
 use super::*;
+use crate::sparse_state::count_values;
 
 /// # An owned dynamic ref
 ///
@@ -33,7 +34,7 @@ where
         metadata: &SparseMetadata,
         depth: u32,
     ) -> Result<(), SparseError> {
-        self.check_depth(depth)?;
+        self.check_depth(state, depth)?;
         match *self.val {
             SparsePointedValue::Null => self.self_reset(state, metadata, depth)?,
             _ => {
@@ -51,7 +52,7 @@ where
         metadata: &SparseMetadata,
         depth: u32,
     ) -> Result<(), SparseError> {
-        self.check_depth(depth)?;
+        self.check_depth(state, depth)?;
         let vcheck = self.check_version(state);
         match vcheck {
             Ok(()) => (),
@@ -70,8 +71,7 @@ where
     S: DeserializeOwned + Serialize + SparsableTrait,
 {
     fn check_version<'a>(&'a self, state: &'a SparseState) -> Result<(), SparseError> {
-        let res =
-            state.get_state_file(self.utils().pfile_path())?.version() == self.utils().version();
+        let res = state.get_state_file_for(self.utils())?.version() == self.utils().version();
         if !res {
             Err(SparseError::OutdatedPointer)
         } else {
@@ -85,12 +85,10 @@ where
 
     fn get_mut(
         &mut self,
-        state_cell: Rc<RefCell<SparseState>>,
+        state_cell: SparseStateCell,
     ) -> Result<SparseValueMut<'_, S>, SparseError> {
         {
-            let state = state_cell
-                .try_borrow()
-                .map_err(|_e| SparseError::StateAlreadyBorrowed)?;
+            let state = state_cell.try_borrow_state()?;
             self.check_version(&state)?;
         }
         self.val.get_mut(state_cell, Some(&self.utils))
@@ -102,7 +100,7 @@ where
         metadata: &SparseMetadata,
         depth: u32,
     ) -> Result<(), SparseError> {
-        self.check_depth(depth)?;
+        self.check_depth(state, depth)?;
         self._self_reset(state, metadata, depth)
     }
 }
@@ -116,9 +114,8 @@ where
         state: &'a mut SparseState,
         utils: &SparseMetadata,
     ) -> Result<&'a SparseStateFile, SparseError> {
-        let pfile_path: &PathBuf = utils.pfile_path();
-        state.add_file(pfile_path.clone())?;
-        state.get_state_file(pfile_path)
+        state.add_file_for(utils)?;
+        state.get_state_file_for(utils)
     }
 
     /// Initialize the inner value using the [SparseState](SparseState).
@@ -127,24 +124,34 @@ where
         utils: &mut SparseMetadata,
         depth: u32,
     ) -> Result<SparsePointedValue<S>, SparseError> {
-        let state_file = SparseRef::<S>::get_state_file_init(state, utils)?;
+        let state_file = SparseRef::<S>::get_state_file_init(state, utils)
+            .map_err(|err| utils.locate_err(err))?;
 
-        let mut val: SparsePointedValue<S> = serde_json::from_value(
-            state_file
-                .val()
-                .pointer(utils.pointer())
-                .ok_or_else(|| SparseError::UnkownPath(utils.pointer().clone()))?
-                .clone(),
-        )?;
+        let target = utils
+            .pointer()
+            .get(state_file.val())
+            .ok_or_else(|| SparseError::UnkownPath(utils.pointer().to_string()))
+            .map_err(|err| utils.locate_err(err))?;
+        let target_values = count_values(target);
+        let mut val: SparsePointedValue<S> = utils
+            .deserialize_pointed(target)
+            .map_err(|err| utils.locate_err(err))?;
         val = match val {
             SparsePointedValue::RefRaw(mut x) => {
                 *x.base_path_mut() = utils.pfile_path().clone();
+                #[cfg(feature = "url")]
+                {
+                    *x.base_url_mut() = utils.pfile_url().clone();
+                }
                 SparsePointedValue::RefRaw(x)
             }
             _ => val,
         };
         *utils.version_mut() = state_file.version();
-        val.sparse_init(state, utils, depth + 1)?;
+        state.enter_ref(utils, target_values)?;
+        let res = val.sparse_init(state, utils, depth + 1);
+        state.leave_ref();
+        res?;
         Ok(val)
     }
 
@@ -161,13 +168,22 @@ where
     }
 
     /// Create a new [SparseRef](SparseRef)
-    pub(crate) fn new(
+    pub fn new(
         state: &mut SparseState,
         path: PathBuf,
         raw_ptr: String,
         depth: u32,
     ) -> Result<Self, SparseError> {
-        let mut utils = SparseMetadata::new(raw_ptr, path);
+        SparseRef::new_from_metadata(state, SparseMetadata::new(raw_ptr, path)?, depth)
+    }
+
+    /// Create a new [SparseRef](SparseRef) from an already parsed [SparseMetadata](crate::SparseMetadata)
+    pub(crate) fn new_from_metadata(
+        state: &mut SparseState,
+        utils: SparseMetadata,
+        depth: u32,
+    ) -> Result<Self, SparseError> {
+        let mut utils = utils;
         let val: Box<SparsePointedValue<S>> =
             Box::new(SparseRef::init_val(state, &mut utils, depth)?);
         Ok(SparseRef { val, utils })
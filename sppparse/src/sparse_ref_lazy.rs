@@ -0,0 +1,116 @@
+use super::*;
+use crate::sparse_document_id::SparseDocumentId;
+
+/// # A lazy dynamic ref
+///
+/// [SparseRefLazy](SparseRefLazy) will render the pointed value only the first time it's accessed.
+///
+/// Initializing a [SparseRefLazy](SparseRefLazy) doesn't resolve the pointed value, which
+/// makes it suitable for recursive data models (i.e. a `Node` whose children are references to
+/// `Node`). Once resolved, the pointed value is cached in the [SparseState](crate::SparseState)
+/// and shared with every other [SparseRefLazy](SparseRefLazy) pointing to the same value.
+///
+/// Because the pointed value is shared, it's read-only. It's owned by the state and by the
+/// [SparseLazyValue](crate::SparseLazyValue) returned when accessing it, the
+/// [SparseRefLazy](SparseRefLazy) only keeping a weak reference to it, so that a recursive
+/// value doesn't own itself.
+///
+/// If the [SparseStateFile](crate::SparseStateFile) used to render the object changes,
+/// [SparseRefLazy](SparseRefLazy) will resolve it again at the next access after an update.
+#[derive(Debug, Clone, Deserialize, Serialize, Getters)]
+pub struct SparseRefLazy<S> {
+    /// The version of the document and the pointed value, once resolved
+    #[serde(skip)]
+    #[serde(default = "SparseOnceCell::new")]
+    val: SparseOnceCell<(u64, SparseWeak<SparsePointedValue<S>>)>,
+    /// The raw `JSON` pointer, as it is deserialized
+    #[serde(rename = "$ref")]
+    raw_pointer: SparseRawPointer,
+    /// Metadata about the pointer
+    #[serde(skip)]
+    #[getset(get = "pub")]
+    utils: SparseMetadata,
+}
+
+impl<S> SparsableTrait for SparseRefLazy<S>
+where
+    S: 'static + DeserializeOwned + Serialize + SparsableTrait + SparseSendSync,
+{
+    fn sparse_init(
+        &mut self,
+        state: &mut SparseState,
+        metadata: &SparseMetadata,
+        depth: u32,
+    ) -> Result<(), SparseError> {
+        self.check_depth(state, depth)?;
+        let location = state.locate_ref(metadata, &mut self.raw_pointer);
+        let utils = SparseMetadata::new_from_parent(self.raw_pointer().clone(), metadata)
+            .map_err(|err| location.wrap_err(err))?;
+        if SparseDocumentId::new(&utils) != SparseDocumentId::new(&self.utils)
+            || utils.pointer() != self.utils.pointer()
+        {
+            self.utils = utils;
+            self.val = SparseOnceCell::new();
+        }
+        self.utils.set_location(location);
+        if let Some(SparseError::OutdatedPointer) = self.check_version(state).err() {
+            self.val = SparseOnceCell::new();
+        }
+        Ok(())
+    }
+}
+
+impl<S> SparseRefLazy<S>
+where
+    S: 'static + DeserializeOwned + Serialize + SparsableTrait + SparseSendSync,
+{
+    /// Create a new [SparseRefLazy](SparseRefLazy), without resolving it
+    pub fn new(path: PathBuf, raw_pointer: String) -> Result<Self, SparseError> {
+        Ok(SparseRefLazy {
+            val: SparseOnceCell::new(),
+            utils: SparseMetadata::new(raw_pointer.clone(), path)?,
+            raw_pointer: SparseRawPointer::new(raw_pointer),
+        })
+    }
+
+    /// The raw `JSON` pointer, as it is deserialized
+    pub fn raw_pointer(&self) -> &String {
+        self.raw_pointer.raw()
+    }
+
+    /// True if the pointed value has already been resolved
+    pub fn is_resolved(&self) -> bool {
+        self.val.get().is_some()
+    }
+
+    /// Check if the resolved value is outdated
+    pub fn check_version(&self, state: &SparseState) -> Result<(), SparseError> {
+        match self.val.get() {
+            Some((version, _val))
+                if state.get_state_file_for(self.utils())?.version() != *version =>
+            {
+                Err(SparseError::OutdatedPointer)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Get the pointed value, resolving it using the state if it's the first access
+    pub fn get(&self, state_cell: SparseStateCell) -> Result<SparseLazyValue<S>, SparseError> {
+        if let Some(val) = self.val.get().and_then(|(_version, val)| val.upgrade()) {
+            return SparseLazyValue::new(val, self.utils().clone());
+        }
+        let (version, val) = state_cell
+            .try_borrow_state_mut()?
+            .resolve_shared::<S>(self.utils())?;
+        let _ = self.val.set((version, SparseRc::downgrade(&val)));
+        SparseLazyValue::new(val, self.utils().clone())
+    }
+}
+
+impl<S> PartialEq for SparseRefLazy<S> {
+    fn eq(&self, other: &Self) -> bool {
+        // The resolved values are owned by the state
+        self.raw_pointer == other.raw_pointer && self.utils == other.utils
+    }
+}
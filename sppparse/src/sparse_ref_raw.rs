@@ -1,5 +1,8 @@
 use super::*;
 
+#[cfg(feature = "url")]
+use url_inner::Url;
+
 /// # A raw dynamic ref
 ///
 /// [SparseRefRaw](SparseRefRaw) will render the pointed value.
@@ -19,12 +22,16 @@ pub struct SparseRefRaw<S> {
     val: SparsePointedValue<S>,
     /// The raw `JSON` pointer, as it is deserialized
     #[serde(rename = "$ref")]
-    #[getset(get = "pub")]
-    raw_pointer: String,
+    raw_pointer: SparseRawPointer,
     /// The path of the file in which originates this pointer, if any
     #[serde(skip)]
     #[getset(get = "pub", get_mut = "pub")]
     base_path: PathBuf,
+    /// The URL of the distant document in which originates this pointer, if any
+    #[cfg(feature = "url")]
+    #[serde(skip)]
+    #[getset(get = "pub", get_mut = "pub")]
+    base_url: Option<Url>,
 }
 
 impl<S> SparsableTrait for SparseRefRaw<S>
@@ -37,7 +44,7 @@ where
         metadata: &SparseMetadata,
         depth: u32,
     ) -> Result<(), SparseError> {
-        self.check_depth(depth)?;
+        self.check_depth(state, depth)?;
         match self.val {
             SparsePointedValue::Null => self.self_reset(state, metadata, depth)?,
             _ => {
@@ -56,7 +63,7 @@ where
         metadata: &SparseMetadata,
         depth: u32,
     ) -> Result<(), SparseError> {
-        self.check_depth(depth)?;
+        self.check_depth(state, depth)?;
         let vcheck = self.check_version(state);
         match vcheck {
             Ok(()) => (),
@@ -86,13 +93,11 @@ where
 
     fn get_mut<'a>(
         &'a mut self,
-        state_cell: Rc<RefCell<SparseState>>,
+        state_cell: SparseStateCell,
         metadata: Option<&'a SparseMetadata>,
     ) -> Result<SparseValueMut<'a, S>, SparseError> {
         {
-            let state = state_cell
-                .try_borrow()
-                .map_err(|_e| SparseError::StateAlreadyBorrowed)?;
+            let state = state_cell.try_borrow_state()?;
             self.check_version(&state)?;
         }
         self.val_mut().get_mut(state_cell, metadata)
@@ -104,7 +109,7 @@ where
         metadata: &SparseMetadata,
         depth: u32,
     ) -> Result<(), SparseError> {
-        self.check_depth(depth)?;
+        self.check_depth(state, depth)?;
         self._self_reset(state, metadata, depth)
     }
 }
@@ -122,23 +127,41 @@ where
     ) -> Result<(), SparseError> {
         match self.val {
             SparsePointedValue::Null => {
-                let val = &mut self.val;
-                let path = match self.base_path.is_absolute() {
-                    true => self.base_path.clone(),
-                    false => metadata.pfile_path().clone(),
-                };
-                *val = SparsePointedValue::Ref(SparseRef::new(
-                    state,
-                    path,
-                    self.raw_pointer.clone(),
-                    depth,
-                )?);
+                let location = state.locate_ref(metadata, &mut self.raw_pointer);
+                let mut utils = self
+                    .pointer_metadata(metadata)
+                    .map_err(|err| location.wrap_err(err))?;
+                utils.set_location(location);
+                self.val =
+                    SparsePointedValue::Ref(SparseRef::new_from_metadata(state, utils, depth)?);
                 Ok(())
             }
             _ => Ok(()),
         }
     }
 
+    /// Parse the pointer, relative to the document it originates from
+    #[cfg(not(feature = "url"))]
+    fn pointer_metadata(&self, metadata: &SparseMetadata) -> Result<SparseMetadata, SparseError> {
+        match self.base_path.is_absolute() {
+            true => SparseMetadata::new(self.raw_pointer().clone(), self.base_path.clone()),
+            false => SparseMetadata::new_from_parent(self.raw_pointer().clone(), metadata),
+        }
+    }
+
+    /// Parse the pointer, relative to the document it originates from
+    #[cfg(feature = "url")]
+    fn pointer_metadata(&self, metadata: &SparseMetadata) -> Result<SparseMetadata, SparseError> {
+        match self.base_path.is_absolute() {
+            true => SparseMetadata::new_with_url(
+                self.raw_pointer().clone(),
+                self.base_path.clone(),
+                self.base_url.as_ref(),
+            ),
+            false => SparseMetadata::new_from_parent(self.raw_pointer().clone(), metadata),
+        }
+    }
+
     /// Reset the inner value in case of change, to reinitialize the inner value
     fn _self_reset(
         &mut self,
@@ -150,11 +173,18 @@ where
         self.init_val(state, metadata, depth)
     }
 
+    /// The raw `JSON` pointer, as it is deserialized
+    pub fn raw_pointer(&self) -> &String {
+        self.raw_pointer.raw()
+    }
+
     pub fn new(raw_pointer: String) -> Self {
         SparseRefRaw {
             val: SparsePointedValue::Null,
-            raw_pointer,
+            raw_pointer: SparseRawPointer::new(raw_pointer),
             base_path: PathBuf::new(),
+            #[cfg(feature = "url")]
+            base_url: None,
         }
     }
 }
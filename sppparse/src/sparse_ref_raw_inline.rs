@@ -1,4 +1,6 @@
 use super::*;
+use crate::sparse_document_id::SparseDocumentId;
+use crate::sparse_state::count_values;
 
 /// ## An owned dynamic ref
 ///
@@ -23,8 +25,7 @@ pub struct SparseRefRawInline<S> {
     #[serde(default = "SparsePointedValue::<S>::default_boxed")]
     val: Box<SparsePointedValue<S>>,
     #[serde(rename = "$ref")]
-    #[getset(get = "pub")]
-    raw_pointer: String,
+    raw_pointer: SparseRawPointer,
     /// Metadata about the pointer
     #[serde(skip)]
     #[getset(get = "pub")]
@@ -41,7 +42,7 @@ where
         metadata: &SparseMetadata,
         depth: u32,
     ) -> Result<(), SparseError> {
-        self.check_depth(depth)?;
+        self.check_depth(state, depth)?;
         match *self.val {
             SparsePointedValue::Null => self.self_reset(state, metadata, depth)?,
             _ => {
@@ -59,7 +60,7 @@ where
         metadata: &SparseMetadata,
         depth: u32,
     ) -> Result<(), SparseError> {
-        self.check_depth(depth)?;
+        self.check_depth(state, depth)?;
         let vcheck = self.check_version(state);
         match vcheck {
             Ok(()) => (),
@@ -78,8 +79,7 @@ where
     S: DeserializeOwned + Serialize + SparsableTrait,
 {
     fn check_version<'a>(&'a self, state: &'a SparseState) -> Result<(), SparseError> {
-        let res =
-            state.get_state_file(self.utils().pfile_path())?.version() == self.utils().version();
+        let res = state.get_state_file_for(self.utils())?.version() == self.utils().version();
         if !res {
             Err(SparseError::OutdatedPointer)
         } else {
@@ -93,12 +93,10 @@ where
 
     fn get_mut(
         &mut self,
-        state_cell: Rc<RefCell<SparseState>>,
+        state_cell: SparseStateCell,
     ) -> Result<SparseValueMut<'_, S>, SparseError> {
         {
-            let state = state_cell
-                .try_borrow()
-                .map_err(|_e| SparseError::StateAlreadyBorrowed)?;
+            let state = state_cell.try_borrow_state()?;
             self.check_version(&state)?;
         }
         self.val.get_mut(state_cell, Some(&self.utils))
@@ -110,8 +108,14 @@ where
         metadata: &SparseMetadata,
         depth: u32,
     ) -> Result<(), SparseError> {
-        self.check_depth(depth)?;
-        self.utils = SparseMetadata::new(self.raw_pointer().clone(), metadata.pfile_path().clone());
+        self.check_depth(state, depth)?;
+        let location = state.locate_ref(metadata, &mut self.raw_pointer);
+        if let Some(pointer) = self.raw_pointer.location() {
+            state.add_inline_ref(SparseDocumentId::new(metadata), pointer.clone());
+        }
+        self.utils = SparseMetadata::new_from_parent(self.raw_pointer().clone(), metadata)
+            .map_err(|err| location.wrap_err(err))?;
+        self.utils.set_location(location);
         self._self_reset(state, metadata, depth)
     }
 }
@@ -125,9 +129,8 @@ where
         state: &'a mut SparseState,
         utils: &SparseMetadata,
     ) -> Result<&'a SparseStateFile, SparseError> {
-        let pfile_path: &PathBuf = utils.pfile_path();
-        state.add_file(pfile_path.clone())?;
-        state.get_state_file(pfile_path)
+        state.add_file_for(utils)?;
+        state.get_state_file_for(utils)
     }
 
     /// Initialize the inner value using the [SparseState](SparseState).
@@ -136,24 +139,34 @@ where
         utils: &mut SparseMetadata,
         depth: u32,
     ) -> Result<SparsePointedValue<S>, SparseError> {
-        let state_file = SparseRefRawInline::<S>::get_state_file_init(state, utils)?;
+        let state_file = SparseRefRawInline::<S>::get_state_file_init(state, utils)
+            .map_err(|err| utils.locate_err(err))?;
 
-        let mut val: SparsePointedValue<S> = serde_json::from_value(
-            state_file
-                .val()
-                .pointer(utils.pointer())
-                .ok_or_else(|| SparseError::UnkownPath(utils.pointer().clone()))?
-                .clone(),
-        )?;
+        let target = utils
+            .pointer()
+            .get(state_file.val())
+            .ok_or_else(|| SparseError::UnkownPath(utils.pointer().to_string()))
+            .map_err(|err| utils.locate_err(err))?;
+        let target_values = count_values(target);
+        let mut val: SparsePointedValue<S> = utils
+            .deserialize_pointed(target)
+            .map_err(|err| utils.locate_err(err))?;
         val = match val {
             SparsePointedValue::RefRaw(mut x) => {
                 *x.base_path_mut() = utils.pfile_path().clone();
+                #[cfg(feature = "url")]
+                {
+                    *x.base_url_mut() = utils.pfile_url().clone();
+                }
                 SparsePointedValue::RefRaw(x)
             }
             _ => val,
         };
         *utils.version_mut() = state_file.version();
-        val.sparse_init(state, utils, depth + 1)?;
+        state.enter_ref(utils, target_values)?;
+        let res = val.sparse_init(state, utils, depth + 1);
+        state.leave_ref();
+        res?;
         Ok(val)
     }
 
@@ -176,13 +189,18 @@ where
         raw_pointer: String,
         depth: u32,
     ) -> Result<Self, SparseError> {
-        let mut utils = SparseMetadata::new(raw_pointer.clone(), path);
+        let mut utils = SparseMetadata::new(raw_pointer.clone(), path)?;
         let val: Box<SparsePointedValue<S>> =
             Box::new(SparseRefRawInline::init_val(state, &mut utils, depth)?);
         Ok(SparseRefRawInline {
             val,
-            raw_pointer,
+            raw_pointer: SparseRawPointer::new(raw_pointer),
             utils,
         })
     }
+
+    /// The raw `JSON` pointer, as it is deserialized
+    pub fn raw_pointer(&self) -> &String {
+        self.raw_pointer.raw()
+    }
 }
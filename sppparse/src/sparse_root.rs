@@ -1,7 +1,6 @@
 use super::*;
 use getset::{CopyGetters, Getters, MutGetters};
 use serde::Serialize;
-use std::cell::RefCell;
 use std::fmt::{self, Display};
 
 /// # A structure to hold the root document as well as its state.
@@ -10,7 +9,7 @@ pub struct SparseRoot<S: DeserializeOwned + Serialize + SparsableTrait> {
     #[getset(get = "pub(crate)", get_mut = "pub(crate)")]
     val: S,
     #[getset(get = "pub")]
-    state: Rc<RefCell<SparseState>>,
+    state: SparseStateCell,
     #[getset(get = "pub")]
     metadata: SparseMetadata,
 }
@@ -31,10 +30,7 @@ where
     /// Get the value this selector is managing, either by deserializing
     /// the pointed value or by directly returning the owned value.
     pub fn check_version(&'_ self) -> Result<(), SparseError> {
-        let state = self
-            .state
-            .try_borrow()
-            .map_err(|_x| SparseError::StateAlreadyBorrowed)?;
+        let state = self.state.try_borrow_state()?;
         let root_file: &SparseStateFile = state
             .get_state_file(state.get_root_path())
             .map_err(|_e| SparseError::NoRoot)?;
@@ -60,28 +56,21 @@ where
     /// Reset the root object in case of initialization or update
     pub fn root_self_reset(&mut self) -> Result<(), SparseError> {
         {
-            let state = self
-                .state
-                .try_borrow()
-                .map_err(|_x| SparseError::StateAlreadyBorrowed)?;
+            let state = self.state.try_borrow_state()?;
             let root_file: &SparseStateFile = state
                 .get_state_file(state.get_root_path())
                 .map_err(|_e| SparseError::NoRoot)?;
-            self.val = serde_json::from_value(root_file.val().clone())?;
+            self.val = self.metadata.deserialize(root_file.val())?;
         }
         self.sparse_init()
     }
 
     /// Intitialize the inner state
     pub fn sparse_init(&mut self) -> Result<(), SparseError> {
-        self.val.sparse_init(
-            &mut *self
-                .state
-                .try_borrow_mut()
-                .map_err(|_x| SparseError::StateAlreadyBorrowed)?,
-            &self.metadata().clone(),
-            0,
-        )
+        let mut state = self.state.try_borrow_state_mut()?;
+        state.reset_counts();
+        self.val
+            .sparse_init(&mut state, &self.metadata().clone(), 0)
     }
 
     /// Update the inner state
@@ -99,20 +88,38 @@ where
 
     /// Create a new [SparseRoot](crate::SparseRoot) from file path
     pub fn new_from_file(path: PathBuf) -> Result<Self, SparseError> {
-        let mut state: SparseState = SparseState::new_from_file(path)?;
+        SparseRoot::new_from_file_with_loader(path, SparseRc::new(SparseFileLoader))
+    }
+
+    /// Create a new [SparseRoot](crate::SparseRoot) from file path, fetching the documents
+    /// using the provided [SparseLoader](crate::SparseLoader)
+    pub fn new_from_file_with_loader(
+        path: PathBuf,
+        loader: SparseRc<dyn SparseLoader>,
+    ) -> Result<Self, SparseError> {
+        SparseRoot::new_from_state(SparseState::new_from_file_with_loader(path, loader)?)
+    }
+
+    /// Create a new [SparseRoot](crate::SparseRoot) from a state, parsing its root document
+    pub fn new_from_state(mut state: SparseState) -> Result<Self, SparseError> {
         let val: S = state.parse_root()?;
         let root_path = state.get_root_path().clone();
         let version: u64 = state.get_state_file(&root_path)?.version();
-        let mut metadata = SparseMetadata::new(String::from("/"), root_path);
+        let mut metadata = SparseMetadata::new(String::from("#"), root_path)?;
 
         *metadata.version_mut() = version;
         Ok(SparseRoot {
             val,
-            state: Rc::new(RefCell::new(state)),
+            state: SparseRc::new(SparseCell::new(state)),
             metadata,
         })
     }
 
+    /// Create a [SparseRootBuilder](crate::SparseRootBuilder) to configure a new [SparseRoot](crate::SparseRoot)
+    pub fn builder() -> SparseRootBuilder<S> {
+        SparseRootBuilder::new()
+    }
+
     /// Create a new [SparseRoot](crate::SparseRoot) from a Value object
     pub fn new_from_value(
         rval: Value,
@@ -120,20 +127,47 @@ where
         others: Vec<(Value, PathBuf)>,
     ) -> Result<Self, SparseError> {
         let mut state: SparseState = SparseState::new_from_value(path, rval)?;
-        let root_path = state.get_root_path().clone();
-        let version: u64 = state.get_state_file(&root_path)?.version();
         for (val, path) in others.into_iter() {
             state.add_value(path, val)?;
         }
-        let val = state.parse_root()?;
-        let mut metadata = SparseMetadata::new(String::from("/"), root_path);
+        SparseRoot::new_from_state(state)
+    }
 
-        *metadata.version_mut() = version;
-        Ok(SparseRoot {
-            val,
-            state: Rc::new(RefCell::new(state)),
-            metadata,
-        })
+    /// Create a new [SparseRoot](crate::SparseRoot) from a raw document, living at the virtual path `path`.
+    ///
+    /// If `format` is `None`, it's detected from `path` then `raw`, like the documents read
+    /// from a file. As with [new_from_value](crate::SparseRoot::new_from_value), the document
+    /// cannot reference distant files, see [SparseRoot::builder](crate::SparseRoot::builder) to allow them,
+    /// and it's dirty until it's saved at `path`.
+    pub fn new_from_str(
+        raw: &str,
+        path: PathBuf,
+        format: Option<SparseFileFormat>,
+    ) -> Result<Self, SparseError> {
+        SparseRoot::builder().build_from_str(path, raw, format)
+    }
+
+    /// Create a new [SparseRoot](crate::SparseRoot) from a raw document, living at the virtual path `path`.
+    ///
+    /// See [new_from_str](crate::SparseRoot::new_from_str)
+    pub fn new_from_slice(
+        raw: &[u8],
+        path: PathBuf,
+        format: Option<SparseFileFormat>,
+    ) -> Result<Self, SparseError> {
+        SparseRoot::builder().build_from_slice(path, raw, format)
+    }
+
+    /// Create a new [SparseRoot](crate::SparseRoot) from a document read from `reader`,
+    /// living at the virtual path `path`.
+    ///
+    /// See [new_from_str](crate::SparseRoot::new_from_str)
+    pub fn new_from_reader<R: std::io::Read>(
+        reader: R,
+        path: PathBuf,
+        format: Option<SparseFileFormat>,
+    ) -> Result<Self, SparseError> {
+        SparseRoot::builder().build_from_reader(path, reader, format)
     }
 
     /// Create a new [SparseRoot](crate::SparseRoot) from a serialized object
@@ -149,23 +183,43 @@ where
         }
         let val: S = state.parse_file(path.clone())?;
         let version: u64 = state.get_state_file(state.get_root_path())?.version();
-        let mut metadata = SparseMetadata::new(String::from("/"), path);
+        let mut metadata = SparseMetadata::new(String::from("#"), path)?;
 
         *metadata.version_mut() = version;
         Ok(SparseRoot {
             val,
-            state: Rc::new(RefCell::new(state)),
+            state: SparseRc::new(SparseCell::new(state)),
             metadata,
         })
     }
 
-    /// Save the state to disk in the specified format.
+    /// Save the modified documents of the state to disk in the specified format, returning the paths written.
     /// If not format is specified, the format in which the document was read will be used.
-    /// If the document was read from memory, it'll be written in prettified JSON
-    pub fn save_to_disk(&self, format: Option<SparseFileFormat>) -> Result<(), SparseError> {
+    /// If the document was read from memory, it'll be written in the format matching its extension, or in prettified JSON
+    pub fn save_to_disk(
+        &self,
+        format: Option<SparseFileFormat>,
+    ) -> Result<Vec<PathBuf>, SparseError> {
+        self.state.try_borrow_state_mut()?.save_to_disk(format)
+    }
+
+    /// Bundle the root document and every document it references into a single [Value](serde_json::Value).
+    ///
+    /// See [SparseState::bundle](crate::SparseState::bundle)
+    pub fn bundle(&self) -> Result<Value, SparseError> {
+        self.state.try_borrow_state_mut()?.bundle()
+    }
+
+    /// Export the root document as a [Value](serde_json::Value), replacing every reference
+    /// by the value it's pointing to.
+    ///
+    /// See [SparseState::to_dereferenced_value](crate::SparseState::to_dereferenced_value)
+    pub fn to_dereferenced_value(
+        &self,
+        behavior: SparseCycleBehavior,
+    ) -> Result<Value, SparseError> {
         self.state
-            .try_borrow()
-            .map_err(|_e| SparseError::StateAlreadyBorrowed)?
-            .save_to_disk(format)
+            .try_borrow_state_mut()?
+            .to_dereferenced_value(behavior)
     }
 }
@@ -8,7 +8,7 @@ use std::any::Any;
 ///
 /// The [SparseSelector](SparseSelector) allows to switch between a raw, unparsed pointer
 /// to a parsed pointer resolved at initialization.
-#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Default)]
 #[serde(untagged)]
 pub enum SparseSelector<T> {
     /// A deserialized JSON pointer contained the pointed value from the local
@@ -18,6 +18,7 @@ pub enum SparseSelector<T> {
     Obj(SparsePointedValue<T>),
     /// A default value that should not be present once the
     /// [SparseRoot](crate::SparseRoot) document has been initialized.
+    #[default]
     Null,
 }
 
@@ -31,7 +32,7 @@ where
         metadata: &SparseMetadata,
         depth: u32,
     ) -> Result<(), SparseError> {
-        self.check_depth(depth)?;
+        self.check_depth(state, depth)?;
         self.self_reset(state, metadata, depth)?;
         self.check_version(state)?;
         match self {
@@ -47,7 +48,7 @@ where
         metadata: &SparseMetadata,
         depth: u32,
     ) -> Result<(), SparseError> {
-        self.check_depth(depth)?;
+        self.check_depth(state, depth)?;
         let vcheck = self.check_version(state);
         match vcheck {
             Ok(()) => (),
@@ -62,15 +63,6 @@ where
     }
 }
 
-impl<T> std::default::Default for SparseSelector<T>
-where
-    T: Any + DeserializeOwned + Serialize + SparsableTrait,
-{
-    fn default() -> Self {
-        SparseSelector::Null
-    }
-}
-
 impl<T> SparsePointer<T> for SparseSelector<T>
 where
     T: Any + DeserializeOwned + Serialize + SparsableTrait,
@@ -91,10 +83,7 @@ where
         }
     }
 
-    fn get_mut(
-        &mut self,
-        root: Rc<RefCell<SparseState>>,
-    ) -> Result<SparseValueMut<'_, T>, SparseError> {
+    fn get_mut(&mut self, root: SparseStateCell) -> Result<SparseValueMut<'_, T>, SparseError> {
         match self {
             SparseSelector::Obj(x) => Ok(x.get_mut(root, None)?),
             SparseSelector::Ref(x) => Ok(x.get_mut(root, None)?),
@@ -108,7 +97,7 @@ where
         metadata: &SparseMetadata,
         depth: u32,
     ) -> Result<(), SparseError> {
-        self.check_depth(depth)?;
+        self.check_depth(state, depth)?;
         match self {
             SparseSelector::Obj(x) => Ok(x.self_reset(state, metadata, depth)?),
             SparseSelector::Ref(x) => Ok(x.self_reset(state, metadata, depth)?),
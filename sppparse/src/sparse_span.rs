@@ -0,0 +1,358 @@
+use super::*;
+use std::fmt::{self, Display};
+use yaml_rust::parser::{Event, Parser};
+
+/// # A position in the original text of a document
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, CopyGetters)]
+pub struct SparseSpan {
+    /// The offset in bytes since the start of the document
+    #[getset(get_copy = "pub")]
+    offset: usize,
+    /// The line, starting at 1
+    #[getset(get_copy = "pub")]
+    line: usize,
+    /// The column in characters, starting at 1
+    #[getset(get_copy = "pub")]
+    column: usize,
+}
+
+impl SparseSpan {
+    /// Create a new [SparseSpan](SparseSpan)
+    pub fn new(offset: usize, line: usize, column: usize) -> Self {
+        SparseSpan {
+            offset,
+            line,
+            column,
+        }
+    }
+}
+
+impl Display for SparseSpan {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// # The location of a value in a document of the state
+#[derive(Debug, Clone, PartialEq, Getters)]
+pub struct SparseLocation {
+    /// The path or the URL of the document
+    #[getset(get = "pub")]
+    file: String,
+    /// The pointer to the value in the document
+    #[getset(get = "pub")]
+    pointer: JsonPointer,
+    /// The position of the value in the original text of the document, if known
+    #[getset(get = "pub")]
+    span: Option<SparseSpan>,
+}
+
+impl SparseLocation {
+    /// Create a new [SparseLocation](SparseLocation)
+    pub fn new(file: String, pointer: JsonPointer, span: Option<SparseSpan>) -> Self {
+        SparseLocation {
+            file,
+            pointer,
+            span,
+        }
+    }
+
+    /// Wrap an error that occurred while resolving the reference living at this location
+    pub(crate) fn wrap_err(&self, err: SparseError) -> SparseError {
+        SparseError::BrokenRef {
+            location: self.clone(),
+            source: Box::new(err),
+        }
+    }
+}
+
+impl Display for SparseLocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.span {
+            Some(span) => write!(f, "{}:{}", self.file, span),
+            None => write!(f, "{}{}", self.file, self.pointer.to_uri_fragment()),
+        }
+    }
+}
+
+/// # Compute the [SparseSpan](SparseSpan) of offsets in a text
+struct SparseLines<'a> {
+    raw: &'a [u8],
+    /// The offset at which each line starts
+    starts: Vec<usize>,
+}
+
+impl<'a> SparseLines<'a> {
+    fn new(raw: &'a [u8]) -> Self {
+        let mut starts = vec![0];
+        starts.extend(
+            raw.iter()
+                .enumerate()
+                .filter(|(_, c)| **c == b'\n')
+                .map(|(idx, _)| idx + 1),
+        );
+        SparseLines { raw, starts }
+    }
+
+    fn span(&self, offset: usize) -> SparseSpan {
+        let line = match self.starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(line) => line - 1,
+        };
+        let column = String::from_utf8_lossy(&self.raw[self.starts[line]..offset])
+            .chars()
+            .count();
+        SparseSpan::new(offset, line + 1, column + 1)
+    }
+}
+
+/// # Record the position of every value of a JSON document
+///
+/// It's lenient enough to read `JSON5` documents, skipping the comments and
+/// accepting single quoted strings and unquoted keys.
+struct SparseJsonSpans<'a> {
+    raw: &'a [u8],
+    pos: usize,
+    offsets: Vec<(JsonPointer, usize)>,
+}
+
+impl<'a> SparseJsonSpans<'a> {
+    /// Skip the whitespaces and the comments
+    fn skip_whitespaces(&mut self) {
+        loop {
+            while self.pos < self.raw.len() && self.raw[self.pos].is_ascii_whitespace() {
+                self.pos += 1;
+            }
+            let rest = &self.raw[self.pos..];
+            let end = match (rest.starts_with(b"//"), rest.starts_with(b"/*")) {
+                (true, _) => rest.iter().position(|c| *c == b'\n'),
+                (_, true) => rest.windows(2).position(|x| x == b"*/").map(|x| x + 2),
+                _ => return,
+            };
+            self.pos += end.unwrap_or(rest.len());
+        }
+    }
+
+    /// Skip a string, returning its unescaped content
+    fn string(&mut self) -> Option<String> {
+        let start = self.pos;
+        let quote = self.raw[start];
+        self.pos += 1;
+        while self.pos < self.raw.len() && self.raw[self.pos] != quote {
+            if self.raw[self.pos] == b'\\' {
+                self.pos += 1;
+            }
+            self.pos += 1;
+        }
+        self.pos += 1;
+        let text = self.raw.get(start..self.pos)?;
+        match quote {
+            b'"' => serde_json::from_slice(text).ok(),
+            _ => Some(String::from_utf8_lossy(&text[1..text.len() - 1]).replace("\\'", "'")),
+        }
+    }
+
+    /// Skip a key, returning its unescaped content
+    fn key(&mut self) -> Option<String> {
+        if matches!(self.raw.get(self.pos)?, b'"' | b'\'') {
+            return self.string();
+        }
+        let start = self.pos;
+        while self.pos < self.raw.len()
+            && self.raw[self.pos] != b':'
+            && !self.raw[self.pos].is_ascii_whitespace()
+        {
+            self.pos += 1;
+        }
+        Some(String::from_utf8_lossy(&self.raw[start..self.pos]).into_owned())
+    }
+
+    fn value(&mut self, pointer: &mut JsonPointer) -> Option<()> {
+        self.skip_whitespaces();
+        self.offsets.push((pointer.clone(), self.pos));
+        match *self.raw.get(self.pos)? {
+            b'{' => {
+                self.pos += 1;
+                loop {
+                    self.skip_whitespaces();
+                    match *self.raw.get(self.pos)? {
+                        b'}' => break,
+                        b',' => self.pos += 1,
+                        _ => {
+                            let key = self.key()?;
+                            self.skip_whitespaces();
+                            self.pos += 1; // The colon
+                            pointer.push(key);
+                            self.value(pointer)?;
+                            pointer.pop();
+                        }
+                    }
+                }
+                self.pos += 1;
+            }
+            b'[' => {
+                self.pos += 1;
+                let mut idx: usize = 0;
+                loop {
+                    self.skip_whitespaces();
+                    match *self.raw.get(self.pos)? {
+                        b']' => break,
+                        b',' => self.pos += 1,
+                        _ => {
+                            pointer.push(idx.to_string());
+                            self.value(pointer)?;
+                            pointer.pop();
+                            idx += 1;
+                        }
+                    }
+                }
+                self.pos += 1;
+            }
+            b'"' | b'\'' => {
+                self.string()?;
+            }
+            _ => {
+                while self.pos < self.raw.len()
+                    && !matches!(self.raw[self.pos], b',' | b']' | b'}' | b'/')
+                    && !self.raw[self.pos].is_ascii_whitespace()
+                {
+                    self.pos += 1;
+                }
+            }
+        }
+        Some(())
+    }
+}
+
+/// Get the length of the `JSON` value at the start of a text
+#[cfg(feature = "json5")]
+pub(crate) fn json_value_len(raw: &[u8]) -> Option<usize> {
+    let mut parser = SparseJsonSpans {
+        raw,
+        pos: 0,
+        offsets: Vec::new(),
+    };
+    parser.value(&mut JsonPointer::new())?;
+    Some(parser.pos)
+}
+
+/// Record the position of every value of a JSON document
+pub(crate) fn json_spans(raw: &[u8]) -> HashMap<JsonPointer, SparseSpan> {
+    let mut parser = SparseJsonSpans {
+        raw,
+        pos: 0,
+        offsets: Vec::new(),
+    };
+    let _ = parser.value(&mut JsonPointer::new());
+    let lines = SparseLines::new(raw);
+    parser
+        .offsets
+        .into_iter()
+        .map(|(pointer, offset)| (pointer, lines.span(offset)))
+        .collect()
+}
+
+/// A collection being walked in a YAML document
+enum SparseYamlFrame {
+    /// A mapping, with the key of the next value if it has been read
+    Mapping(Option<String>),
+    /// A sequence, with the index of the next value
+    Sequence(usize),
+}
+
+/// Record the position of every value of a YAML document
+///
+/// The pointers of a multi-document stream start with the index of their document.
+pub(crate) fn yaml_spans(raw: &[u8]) -> HashMap<JsonPointer, SparseSpan> {
+    let mut res: Vec<HashMap<JsonPointer, SparseSpan>> = Vec::new();
+    let text = String::from_utf8_lossy(raw);
+    // The YAML parser counts in characters
+    let offsets: Vec<usize> = text.char_indices().map(|(offset, _)| offset).collect();
+    let mut parser = Parser::new(text.chars());
+    let mut frames: Vec<SparseYamlFrame> = Vec::new();
+    let mut pointer = JsonPointer::new();
+    // The depth of the complex mapping key being skipped, if any
+    let mut skipping: usize = 0;
+
+    while let Ok((event, mark)) = parser.next() {
+        let (is_value, is_start) = match &event {
+            Event::StreamEnd => break,
+            Event::DocumentStart => {
+                res.push(HashMap::new());
+                frames.clear();
+                pointer = JsonPointer::new();
+                skipping = 0;
+                continue;
+            }
+            Event::Scalar(..) | Event::Alias(_) => (true, false),
+            Event::MappingStart(_) | Event::SequenceStart(_) => (true, true),
+            Event::MappingEnd | Event::SequenceEnd => (false, false),
+            _ => continue,
+        };
+        if skipping > 0 {
+            skipping = match is_start {
+                true => skipping + 1,
+                false if !is_value => skipping - 1,
+                false => skipping,
+            };
+            continue;
+        }
+        if !is_value {
+            frames.pop();
+            pointer.pop();
+            continue;
+        }
+        let token = match frames.last_mut() {
+            Some(SparseYamlFrame::Mapping(key @ None)) => {
+                match (&event, is_start) {
+                    (Event::Scalar(val, ..), _) => *key = Some(val.clone()),
+                    (_, true) => skipping = 1,
+                    _ => (),
+                }
+                continue;
+            }
+            Some(SparseYamlFrame::Mapping(key)) => key.take(),
+            Some(SparseYamlFrame::Sequence(idx)) => {
+                *idx += 1;
+                Some((*idx - 1).to_string())
+            }
+            None => None,
+        };
+        if let Some(token) = &token {
+            pointer.push(token.clone());
+        }
+        let offset = offsets.get(mark.index()).copied().unwrap_or(raw.len());
+        let Some(res) = res.last_mut() else { break };
+        res.insert(
+            pointer.clone(),
+            SparseSpan::new(offset, mark.line(), mark.col() + 1),
+        );
+        match event {
+            Event::MappingStart(_) => frames.push(SparseYamlFrame::Mapping(None)),
+            Event::SequenceStart(_) => frames.push(SparseYamlFrame::Sequence(0)),
+            _ => {
+                if token.is_some() {
+                    pointer.pop();
+                }
+            }
+        }
+    }
+    match res.len() {
+        0 | 1 => res.pop().unwrap_or_default(),
+        _ => res
+            .into_iter()
+            .enumerate()
+            .flat_map(|(idx, spans)| {
+                spans.into_iter().map(move |(pointer, span)| {
+                    let mut res = JsonPointer::new();
+                    res.push(idx.to_string());
+                    pointer
+                        .tokens()
+                        .iter()
+                        .for_each(|token| res.push(token.clone()));
+                    (res, span)
+                })
+            })
+            .collect(),
+    }
+}
@@ -1,21 +1,133 @@
 use super::*;
+use crate::sparse_document_id::SparseDocumentId;
+use crate::sparse_span::{json_spans, yaml_spans};
 use getset::{CopyGetters, Getters, MutGetters};
 use rand::Rng;
-use serde::de::IntoDeserializer;
+use std::any::TypeId;
+use std::collections::HashSet;
 use std::fs;
-use std::io::{Seek, SeekFrom, Write};
+use std::io::Write;
 use std::path::{Path, PathBuf};
 
+#[cfg(feature = "url")]
+use url_inner::Url;
+
 /// # Format in which [Sparse](crate) should read/write the files
-#[derive(Clone, Debug, Copy)]
+///
+/// The boolean of `Json` is true if the document is prettified.
+///
+/// The `toml` and `json5` features add variants, so it's non exhaustive.
+#[derive(Clone, Debug, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum SparseFileFormat {
     Json(bool),
+    #[default]
     Yaml,
+    /// With the `toml` feature
+    #[cfg(feature = "toml")]
+    Toml,
+    /// With the `json5` feature, `JSON5` documents, also accepting `JSON` with comments.
+    ///
+    /// They're saved by patching their original text, keeping the comments, or as
+    /// prettified `JSON`, which is valid `JSON5`.
+    #[cfg(feature = "json5")]
+    Json5,
 }
 
-impl std::default::Default for SparseFileFormat {
-    fn default() -> Self {
-        SparseFileFormat::Yaml
+impl SparseFileFormat {
+    /// Guess the format of a raw document, trying `JSON` then falling back to `YAML`.
+    ///
+    /// With the `json5` feature, `JSON5` is tried after `JSON`.
+    /// With the `toml` feature, `TOML` is tried before `YAML`, only if the document has
+    /// a key or a table, as a document made of comments is also valid `YAML`.
+    pub fn sniff(raw: &[u8]) -> Result<Self, SparseError> {
+        match serde_json::from_slice::<serde::de::IgnoredAny>(raw) {
+            Ok(_) => Ok(SparseFileFormat::Json(SparseFileFormat::is_pretty(raw))),
+            #[cfg(feature = "json5")]
+            Err(json_err)
+                if (json_err.is_syntax() || json_err.is_data())
+                    && SparseFileFormat::is_json5(raw) =>
+            {
+                Ok(SparseFileFormat::Json5)
+            }
+            #[cfg(feature = "toml")]
+            Err(json_err)
+                if (json_err.is_syntax() || json_err.is_data())
+                    && SparseFileFormat::is_toml(raw) =>
+            {
+                Ok(SparseFileFormat::Toml)
+            }
+            Err(json_err) if json_err.is_syntax() || json_err.is_data() => {
+                Ok(SparseFileFormat::Yaml)
+            }
+            Err(json_err) => Err(SparseError::SerdeJson(json_err)),
+        }
+    }
+
+    /// Guess the format of a document from the extension of its path, if known
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_string_lossy().to_ascii_lowercase();
+        match extension.as_str() {
+            "json" => Some(SparseFileFormat::Json(true)),
+            "yaml" | "yml" => Some(SparseFileFormat::Yaml),
+            #[cfg(feature = "toml")]
+            "toml" => Some(SparseFileFormat::Toml),
+            #[cfg(feature = "json5")]
+            "json5" | "jsonc" => Some(SparseFileFormat::Json5),
+            _ => None,
+        }
+    }
+
+    /// Detect the format of a raw document from the extension of its path,
+    /// falling back to its content if the extension is unknown
+    ///
+    /// With the `json5` feature, a `.json` file that is only valid `JSON5` is detected as such.
+    pub fn detect(path: &Path, raw: &[u8]) -> Result<Self, SparseError> {
+        match SparseFileFormat::from_path(path) {
+            #[cfg(feature = "json5")]
+            Some(SparseFileFormat::Json(_))
+                if serde_json::from_slice::<serde::de::IgnoredAny>(raw).is_err()
+                    && SparseFileFormat::is_json5(raw) =>
+            {
+                Ok(SparseFileFormat::Json5)
+            }
+            Some(SparseFileFormat::Json(_)) => {
+                Ok(SparseFileFormat::Json(SparseFileFormat::is_pretty(raw)))
+            }
+            Some(format) => Ok(format),
+            None => SparseFileFormat::sniff(raw),
+        }
+    }
+
+    /// Check if a raw document is valid `JSON5`
+    #[cfg(feature = "json5")]
+    fn is_json5(raw: &[u8]) -> bool {
+        std::str::from_utf8(raw)
+            .map(|text| json5_inner::from_str::<serde::de::IgnoredAny>(text).is_ok())
+            .unwrap_or(false)
+    }
+
+    /// Check if a raw document is `TOML` with at least a key or a table
+    #[cfg(feature = "toml")]
+    fn is_toml(raw: &[u8]) -> bool {
+        toml_inner::from_slice::<toml_inner::value::Table>(raw)
+            .map(|table| !table.is_empty())
+            .unwrap_or(false)
+    }
+
+    /// Check if a raw `JSON` document spans multiple lines
+    fn is_pretty(raw: &[u8]) -> bool {
+        raw.trim_ascii().contains(&b'\n')
+    }
+
+    /// Get the indentation of a prettified raw `JSON` document
+    fn json_indent(raw: &[u8]) -> Option<String> {
+        String::from_utf8_lossy(raw)
+            .lines()
+            .skip(1)
+            .find(|line| !line.trim().is_empty())
+            .map(|line| line[..line.len() - line.trim_start().len()].to_string())
+            .filter(|indent| !indent.is_empty())
     }
 }
 
@@ -33,87 +145,587 @@ pub struct SparseStateFile {
 
     #[getset(get_copy = "pub")]
     ftype: SparseFileFormat,
+    /// The position of each value in the original text of the file, if it was parsed from one
+    #[getset(get = "pub(crate)")]
+    spans: HashMap<JsonPointer, SparseSpan>,
+    /// The text of the file, as it was read or last saved. It's patched when saving
+    /// to keep the comments and the formatting of the unchanged values.
+    #[getset(get = "pub")]
+    raw: Option<String>,
+    /// The indentation of the file, if it's a prettified `JSON` document
+    #[getset(get = "pub")]
+    indent: Option<String>,
+    /// True if the text of the file ends with a new line
+    final_newline: bool,
+    /// True if the file is a multi-document `YAML` stream. Its value is then the array
+    /// of its documents, addressed by their index (`file.yaml#/1/key`), and it's written
+    /// back as multiple documents.
+    #[getset(get_copy = "pub")]
+    stream: bool,
+    /// True if the file was modified since it was read or last saved
+    #[getset(get_copy = "pub")]
+    dirty: bool,
+    /// The pointer of each string of the value, by the address of its text
+    strings: SparseStringIndex,
+}
+
+/// # The pointer of each string of a [Value](serde_json::Value), by the address of its text
+///
+/// It's built on demand to locate the references deserialized from a document, and dropped
+/// when the document is modified.
+#[derive(Default)]
+struct SparseStringIndex(SparseOnceCell<HashMap<usize, JsonPointer>>);
+
+impl SparseStringIndex {
+    /// Index the strings of `val`, located at `pointer`
+    fn index(val: &Value, pointer: &mut JsonPointer, res: &mut HashMap<usize, JsonPointer>) {
+        match val {
+            // The empty strings don't own their text, their address isn't unique
+            Value::String(x) if !x.is_empty() => {
+                res.insert(x.as_ptr() as usize, pointer.clone());
+            }
+            Value::Object(map) => {
+                for (key, inner) in map.iter() {
+                    pointer.push(key.clone());
+                    SparseStringIndex::index(inner, pointer, res);
+                    pointer.pop();
+                }
+            }
+            Value::Array(list) => {
+                for (idx, inner) in list.iter().enumerate() {
+                    pointer.push(idx.to_string());
+                    SparseStringIndex::index(inner, pointer, res);
+                    pointer.pop();
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+impl Clone for SparseStringIndex {
+    fn clone(&self) -> Self {
+        // The addresses are those of the original value
+        SparseStringIndex::default()
+    }
+}
+
+impl std::fmt::Debug for SparseStringIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("SparseStringIndex").finish()
+    }
 }
 
 impl SparseStateFile {
     /// Create a new state file providing the [Value](serde_json::Value).
+    ///
+    /// As it doesn't originate from the disk, it's dirty until it's saved.
     pub fn new(val: Value, ftype: SparseFileFormat) -> Self {
         let mut rng = rand::thread_rng();
         SparseStateFile {
             val,
-            version: rng.gen_range(1..std::u64::MAX),
+            version: rng.gen_range(1..u64::MAX),
             ftype,
+            spans: HashMap::new(),
+            raw: None,
+            indent: None,
+            final_newline: false,
+            stream: false,
+            dirty: true,
+            strings: SparseStringIndex::default(),
         }
     }
 
-    /// Increase the internal version by 1
+    /// Get the position of a value in the original text of the file, if known
+    pub fn span(&self, pointer: &JsonPointer) -> Option<SparseSpan> {
+        self.spans.get(pointer).copied()
+    }
+
+    /// Mark the file as modified, to be written by the next save
+    pub(crate) fn set_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    /// Increase the internal version by 1, marking the file as dirty
     pub fn bump_version(&mut self) {
         self.version = self.version.wrapping_add(1);
+        self.dirty = true;
+        self.strings = SparseStringIndex::default();
+    }
+
+    /// Find the pointer of the string `raw` whose text is at the address `origin`, if it's
+    /// part of the value
+    pub(crate) fn locate_str(&self, origin: usize, raw: &str) -> Option<JsonPointer> {
+        let index = self.strings.0.get_or_init(|| {
+            let mut res = HashMap::new();
+            SparseStringIndex::index(&self.val, &mut JsonPointer::new(), &mut res);
+            res
+        });
+        index
+            .get(&origin)
+            .filter(|pointer| pointer.get(&self.val).and_then(Value::as_str) == Some(raw))
+            .cloned()
     }
 
     /// Replace the [Value](serde_json::Value) of the [SparseStateFile](crate::SparseStateFile) and increment its version.
+    ///
+    /// The positions of the original text are dropped, as they no longer match the value.
     pub fn replace(&mut self, val: Value) {
         self.val = val;
+        self.spans.clear();
         self.bump_version();
     }
 }
 
+/// Count the values making up a value, itself included
+pub(crate) fn count_values(val: &Value) -> u64 {
+    1 + match val {
+        Value::Object(map) => map.values().map(count_values).sum(),
+        Value::Array(list) => list.iter().map(count_values).sum(),
+        _ => 0,
+    }
+}
+
+/// The document, pointer and type of a value shared between [SparseRefLazy](crate::SparseRefLazy)
+type SparseSharedKey = (SparseDocumentId, JsonPointer, TypeId);
+
 /// # State in which the documents are cached
-#[derive(Debug, Clone, Getters, MutGetters)]
+#[derive(Debug, Clone, Getters, MutGetters, CopyGetters)]
 pub struct SparseState {
     /// A map between the absolute path (if any), of the file and their [SparseStateFile](SparseStateFile)
     map_raw: HashMap<PathBuf, SparseStateFile>,
+    /// A map between the URL of the distant documents and their [SparseStateFile](SparseStateFile)
+    #[cfg(feature = "url")]
+    map_url: HashMap<Url, SparseStateFile>,
     /// The path of the file, if it's not in-memory
     root_base: PathBuf,
-    /// True if this is an in-memory state
-    in_memory: bool,
+    /// True if the documents that are not in the state can be fetched using the loader
+    #[getset(get_copy = "pub")]
+    distant_files: bool,
+    /// The format in which the documents are saved, if not specified when saving
+    #[getset(get_copy = "pub")]
+    output_format: Option<SparseFileFormat>,
+    /// True if the documents must be saved by patching their original text
+    #[getset(get_copy = "pub")]
+    lossless: bool,
+    /// The loader used to fetch the documents that are not in the state yet
+    #[getset(get = "pub")]
+    loader: SparseRc<dyn SparseLoader>,
+    /// The references currently being resolved, used to detect the cycles
+    resolving: Vec<(SparseDocumentId, JsonPointer)>,
+    /// The values resolved by [SparseRefLazy](crate::SparseRefLazy), with the version of their document
+    shared: HashMap<SparseSharedKey, (u64, SparseAnyRc)>,
+    /// The location of the [SparseRefRawInline](crate::SparseRefRawInline) met while
+    /// initializing the documents, as their raw pointer is a bare string
+    inline_refs: HashSet<(SparseDocumentId, JsonPointer)>,
+    /// The max stack frames the resolution of a document can go
+    #[getset(get_copy = "pub")]
+    max_depth: u32,
+    /// The restrictions on the documents that can be fetched
+    #[getset(get = "pub")]
+    policy: SparsePolicy,
+    /// The limits on the resources the state can use
+    #[getset(get_copy = "pub")]
+    limits: SparseLimits,
+    /// The number of references resolved since the start of the current initialization
+    refs_count: u64,
+    /// The number of values resolved through references since the start of the current initialization
+    expanded_count: u64,
+    /// The number of documents in the state and the number of values they're made of, when last counted
+    sources_count: (usize, u64),
 }
 
 impl SparseState {
-    /// Read a file
-    fn read_file(path: PathBuf) -> Result<SparseStateFile, SparseError> {
-        let file_json = fs::File::open(path.as_path())?;
-
-        let json_res = serde_json::from_reader(file_json);
-        if let Err(json_err) = json_res {
-            if json_err.is_syntax() || json_err.is_data() {
-                let file_yaml = fs::File::open(path.as_path())?;
-                let val = serde_yaml::from_reader(file_yaml)?;
-                let res = SparseStateFile::new(val, SparseFileFormat::Yaml);
-                Ok(res)
-            } else {
-                Err(SparseError::SerdeJson(json_err))
+    /// Parse a raw document in the specified format
+    pub(crate) fn parse_raw(
+        raw: &[u8],
+        format: SparseFileFormat,
+    ) -> Result<SparseStateFile, SparseError> {
+        let mut stream = false;
+        let (val, spans): (Value, _) = match format {
+            SparseFileFormat::Json(_) => (serde_json::from_slice(raw)?, json_spans(raw)),
+            SparseFileFormat::Yaml => {
+                let (val, is_stream) = SparseState::parse_yaml(raw)?;
+                stream = is_stream;
+                (val, yaml_spans(raw))
+            }
+            #[cfg(feature = "toml")]
+            SparseFileFormat::Toml => (toml_inner::from_slice(raw)?, HashMap::new()),
+            #[cfg(feature = "json5")]
+            SparseFileFormat::Json5 => (
+                json5_inner::from_str(&String::from_utf8_lossy(raw))?,
+                json_spans(raw),
+            ),
+        };
+        let mut res = SparseStateFile::new(val, format);
+        res.stream = stream;
+        res.spans = spans;
+        res.raw = String::from_utf8(raw.to_vec()).ok();
+        match format {
+            SparseFileFormat::Json(true) => res.indent = SparseFileFormat::json_indent(raw),
+            #[cfg(feature = "json5")]
+            SparseFileFormat::Json5 => res.indent = SparseFileFormat::json_indent(raw),
+            _ => (),
+        }
+        res.final_newline = raw.ends_with(b"\n");
+        res.dirty = false;
+        Ok(res)
+    }
+
+    /// Parse a raw `YAML` document, returning the array of its documents if it's a
+    /// multi-document stream, along with true
+    ///
+    /// The aliases are expanded by the parser, then the merge keys are expanded.
+    fn parse_yaml(raw: &[u8]) -> Result<(Value, bool), SparseError> {
+        let mut docs = serde_yaml::Deserializer::from_slice(raw)
+            .map(Value::deserialize)
+            .collect::<Result<Vec<Value>, _>>()?;
+        docs.iter_mut().for_each(SparseState::expand_merge_keys);
+        match docs.len() {
+            0 => Ok((serde_yaml::from_slice(raw)?, false)),
+            1 => Ok((docs.remove(0), false)),
+            _ => Ok((Value::Array(docs), true)),
+        }
+    }
+
+    /// Expand the `YAML` merge keys (`<<: *anchor` or `<<: [*anchor1, *anchor2]`) of a value.
+    ///
+    /// The keys of the mapping take precedence over the merged ones, and the first
+    /// merged mappings over the last ones.
+    fn expand_merge_keys(val: &mut Value) {
+        const MERGE_KEY: &str = "<<";
+        match val {
+            Value::Array(list) => list.iter_mut().for_each(SparseState::expand_merge_keys),
+            Value::Object(map) => {
+                map.values_mut().for_each(SparseState::expand_merge_keys);
+                let sources: Vec<serde_json::Map<String, Value>> = match map.get(MERGE_KEY) {
+                    Some(Value::Object(source)) => vec![source.clone()],
+                    Some(Value::Array(list)) if list.iter().all(Value::is_object) => list
+                        .iter()
+                        .filter_map(|source| source.as_object().cloned())
+                        .collect(),
+                    _ => return,
+                };
+                let explicit: HashSet<String> = map
+                    .keys()
+                    .filter(|key| *key != MERGE_KEY)
+                    .cloned()
+                    .collect();
+                let mut res = serde_json::Map::new();
+                for (key, val) in std::mem::take(map) {
+                    match key.as_str() {
+                        MERGE_KEY => {
+                            for (key, val) in sources.iter().flatten() {
+                                if !explicit.contains(key) && !res.contains_key(key) {
+                                    res.insert(key.clone(), val.clone());
+                                }
+                            }
+                        }
+                        _ => {
+                            res.insert(key, val);
+                        }
+                    }
+                }
+                *map = res;
             }
-        } else {
-            let res = SparseStateFile::new(json_res?, SparseFileFormat::Json(true));
-            Ok(res)
+            _ => (),
         }
     }
 
+    /// The format of a document created from memory, from its extension or prettified `JSON`
+    fn in_memory_format(path: &Path) -> SparseFileFormat {
+        SparseFileFormat::from_path(path).unwrap_or(SparseFileFormat::Json(true))
+    }
+
+    /// Read a file using the provided [SparseLoader](crate::SparseLoader), checking its size
+    pub(crate) fn read_file(
+        loader: &dyn SparseLoader,
+        path: &Path,
+        limits: &SparseLimits,
+    ) -> Result<SparseStateFile, SparseError> {
+        let (raw, format) = loader.load_limited(path, limits)?;
+        SparseState::parse_raw(&raw, format)
+    }
+
     /// Create a new `SparseState` from a root file
     pub fn new_from_file(path: PathBuf) -> Result<Self, SparseError> {
-        let mut map: HashMap<PathBuf, SparseStateFile> = HashMap::new();
+        SparseState::new_from_file_with_loader(path, SparseRc::new(SparseFileLoader))
+    }
+
+    /// Create a new `SparseState` from a root file, fetching the documents using `loader`
+    pub fn new_from_file_with_loader(
+        path: PathBuf,
+        loader: SparseRc<dyn SparseLoader>,
+    ) -> Result<Self, SparseError> {
         let path = SparseMetadata::normalize_path(path, std::env::current_dir()?)?;
-        let res = SparseState::read_file(path.clone())?;
-        map.insert(path.clone(), res);
-        Ok(SparseState {
-            map_raw: map,
-            root_base: path,
-            in_memory: false,
-        })
+        let res = SparseState::read_file(&*loader, path.as_path(), &SparseLimits::default())?;
+        Ok(SparseState::new_with_root(path, res, loader, true))
     }
 
     /// Create a new `SparseState` from an in memory Value
     pub fn new_from_value(path: PathBuf, val: Value) -> Result<Self, SparseError> {
-        let mut map: HashMap<PathBuf, SparseStateFile> = HashMap::new();
         let path = SparseMetadata::normalize_path(path, std::env::current_dir()?)?;
-        let res = SparseStateFile::new(val, SparseFileFormat::Yaml);
-        map.insert(path.clone(), res);
-        Ok(SparseState {
+        let res = SparseStateFile::new(val, SparseState::in_memory_format(&path));
+        Ok(SparseState::new_with_root(
+            path,
+            res,
+            SparseRc::new(SparseFileLoader),
+            false,
+        ))
+    }
+
+    /// Create a new `SparseState` from its root document, at the absolute path `path`
+    pub(crate) fn new_with_root(
+        path: PathBuf,
+        root: SparseStateFile,
+        loader: SparseRc<dyn SparseLoader>,
+        distant_files: bool,
+    ) -> Self {
+        let mut map: HashMap<PathBuf, SparseStateFile> = HashMap::new();
+        map.insert(path.clone(), root);
+        SparseState {
             map_raw: map,
             root_base: path,
-            in_memory: true,
-        })
+            #[cfg(feature = "url")]
+            map_url: HashMap::new(),
+            distant_files,
+            output_format: None,
+            lossless: false,
+            loader,
+            resolving: Vec::new(),
+            shared: HashMap::new(),
+            inline_refs: HashSet::new(),
+            max_depth: MAX_SPARSE_DEPTH,
+            policy: SparsePolicy::default(),
+            limits: SparseLimits::default(),
+            refs_count: 0,
+            expanded_count: 0,
+            sources_count: (0, 0),
+        }
+    }
+
+    /// Create a [SparseStateBuilder](crate::SparseStateBuilder) to configure a new `SparseState`
+    pub fn builder() -> SparseStateBuilder {
+        SparseStateBuilder::new()
+    }
+
+    /// Replace the [SparseLoader](crate::SparseLoader) used to fetch the documents
+    pub fn set_loader(&mut self, loader: SparseRc<dyn SparseLoader>) {
+        self.loader = loader;
+    }
+
+    /// Set the [SparsePolicy](crate::SparsePolicy) restricting the documents that can be fetched.
+    ///
+    /// The documents already in the state are not checked again.
+    pub fn set_policy(&mut self, policy: SparsePolicy) {
+        self.policy = policy;
+    }
+
+    /// Set the format in which the documents are saved when none is specified to
+    /// [save_to_disk](crate::SparseState::save_to_disk). If `None`, each document keeps its own.
+    pub fn set_output_format(&mut self, format: Option<SparseFileFormat>) {
+        self.output_format = format;
+    }
+
+    /// Require, or not, the documents saved in their own format to be saved by patching
+    /// their original text.
+    ///
+    /// By default, a document whose changes can't be patched is serialized again, losing
+    /// its comments and formatting. When lossless, saving it fails with
+    /// [NotPatchable](crate::SparseError::NotPatchable) instead.
+    pub fn set_lossless(&mut self, lossless: bool) {
+        self.lossless = lossless;
+    }
+
+    /// Set the [SparseLimits](crate::SparseLimits) on the resources the state can use.
+    ///
+    /// The documents already in the state are not checked again.
+    pub fn set_limits(&mut self, limits: SparseLimits) {
+        self.limits = limits;
+    }
+
+    /// Start counting the references and the values resolved from zero, at the start of an
+    /// initialization or of the resolution of a [SparseRefLazy](crate::SparseRefLazy)
+    pub(crate) fn reset_counts(&mut self) {
+        self.refs_count = 0;
+        self.expanded_count = 0;
+        self.sources_count = (0, 0);
+    }
+
+    /// Count a reference being resolved, checking the limit of references
+    pub(crate) fn count_ref(&mut self) -> Result<(), SparseError> {
+        self.refs_count += 1;
+        self.limits.check(SparseLimit::Refs, self.refs_count)
+    }
+
+    /// Count the values of a target resolved through a reference, checking the expansion limit
+    pub(crate) fn count_expansion(&mut self, target_values: u64) -> Result<(), SparseError> {
+        if self.limits.max_expansion().is_none() {
+            return Ok(());
+        }
+        if self.sources_count.0 != self.files_count() {
+            self.sources_count = (self.files_count(), self.values_count());
+        }
+        self.expanded_count += target_values;
+        self.limits.check(
+            SparseLimit::Expansion,
+            self.expanded_count / self.sources_count.1.max(1),
+        )
+    }
+
+    /// Get the number of documents in the state
+    pub(crate) fn files_count(&self) -> usize {
+        #[cfg(feature = "url")]
+        return self.map_raw.len() + self.map_url.len();
+        #[cfg(not(feature = "url"))]
+        self.map_raw.len()
+    }
+
+    /// Get the number of values of the documents in the state
+    pub(crate) fn values_count(&self) -> u64 {
+        let res: u64 = self.map_raw.values().map(|x| count_values(x.val())).sum();
+        #[cfg(feature = "url")]
+        let res = res
+            + self
+                .map_url
+                .values()
+                .map(|x| count_values(x.val()))
+                .sum::<u64>();
+        res
+    }
+
+    /// Set the max stack frames the resolution of a document can go.
+    ///
+    /// By default, it's [MAX_SPARSE_DEPTH](crate::MAX_SPARSE_DEPTH)
+    pub fn set_max_depth(&mut self, max_depth: u32) {
+        self.max_depth = max_depth;
+    }
+
+    /// Mark the target of a [SparseMetadata](crate::SparseMetadata) as being resolved.
+    ///
+    /// Fails with a [CyclicRef](crate::SparseError::CyclicRef) if it's already being resolved.
+    /// The target, made of `target_values` values, is counted against the limits.
+    pub(crate) fn enter_ref(
+        &mut self,
+        utils: &SparseMetadata,
+        target_values: u64,
+    ) -> Result<(), SparseError> {
+        self.count_ref()?;
+        self.count_expansion(target_values)?;
+        let id = SparseDocumentId::new(utils);
+        if let Some(pos) = self
+            .resolving
+            .iter()
+            .position(|(curr_id, curr_ptr)| *curr_id == id && curr_ptr == utils.pointer())
+        {
+            let mut chain: Vec<String> = self.resolving[pos..]
+                .iter()
+                .map(|(curr_id, curr_ptr)| curr_id.describe(curr_ptr))
+                .collect();
+            chain.push(id.describe(utils.pointer()));
+            return Err(SparseError::CyclicRef(chain));
+        }
+        self.resolving.push((id, utils.pointer().clone()));
+        Ok(())
+    }
+
+    /// Mark the last reference entered as resolved
+    pub(crate) fn leave_ref(&mut self) {
+        self.resolving.pop();
+    }
+
+    /// Record that the string at `pointer` in the document `id` is a
+    /// [SparseRefRawInline](crate::SparseRefRawInline)
+    pub(crate) fn add_inline_ref(&mut self, id: SparseDocumentId, pointer: JsonPointer) {
+        self.inline_refs.insert((id, pointer));
+    }
+
+    /// Check if the string at `pointer` in the document `id` is a
+    /// [SparseRefRawInline](crate::SparseRefRawInline)
+    pub(crate) fn is_inline_ref(&self, id: &SparseDocumentId, pointer: &JsonPointer) -> bool {
+        self.inline_refs.contains(&(id.clone(), pointer.clone()))
+    }
+
+    /// Resolve the value a [SparseMetadata](crate::SparseMetadata) is pointing to, sharing it
+    /// with the other [SparseRefLazy](crate::SparseRefLazy) pointing to the same value
+    pub(crate) fn resolve_shared<S>(
+        &mut self,
+        utils: &SparseMetadata,
+    ) -> Result<(u64, SparseRc<SparsePointedValue<S>>), SparseError>
+    where
+        S: 'static + DeserializeOwned + Serialize + SparsableTrait + SparseSendSync,
+    {
+        let key = (
+            SparseDocumentId::new(utils),
+            utils.pointer().clone(),
+            TypeId::of::<S>(),
+        );
+        if self.resolving.is_empty() {
+            // Resolving a lazy reference on its own starts a new count of the references
+            self.reset_counts();
+        }
+        self.add_file_for(utils)
+            .map_err(|err| utils.locate_err(err))?;
+        let state_file = self
+            .get_state_file_for(utils)
+            .map_err(|err| utils.locate_err(err))?;
+        let version = state_file.version();
+        if let Some((shared_version, shared)) = self.shared.get(&key) {
+            if let (true, Ok(val)) = (
+                *shared_version == version,
+                shared.clone().downcast::<SparsePointedValue<S>>(),
+            ) {
+                return Ok((version, val));
+            }
+        }
+        let target = utils
+            .pointer()
+            .get(state_file.val())
+            .ok_or_else(|| SparseError::UnkownPath(utils.pointer().to_string()))
+            .map_err(|err| utils.locate_err(err))?;
+        let target_values = count_values(target);
+        let mut val: SparsePointedValue<S> = utils
+            .deserialize_pointed(target)
+            .map_err(|err| utils.locate_err(err))?;
+        let mut utils = utils.clone();
+        if let SparsePointedValue::RefRaw(x) = &mut val {
+            *x.base_path_mut() = utils.pfile_path().clone();
+            #[cfg(feature = "url")]
+            {
+                *x.base_url_mut() = utils.pfile_url().clone();
+            }
+        }
+        *utils.version_mut() = version;
+        self.enter_ref(&utils, target_values)?;
+        let res = val.sparse_init(self, &utils, 0);
+        self.leave_ref();
+        res?;
+        let val = SparseRc::new(val);
+        self.shared.insert(key, (version, val.clone()));
+        Ok((version, val))
+    }
+
+    /// Find where a reference lives in the document described by `parent`, the one its
+    /// raw pointer was deserialized from.
+    ///
+    /// If it can't be found, the location of the value `parent` is pointing to is used instead.
+    pub(crate) fn locate_ref(
+        &self,
+        parent: &SparseMetadata,
+        raw_ptr: &mut SparseRawPointer,
+    ) -> SparseLocation {
+        let file = SparseDocumentId::new(parent).to_string();
+        let state_file = match self.get_state_file_for(parent) {
+            Ok(x) => x,
+            Err(_) => return SparseLocation::new(file, parent.pointer().clone(), None),
+        };
+        raw_ptr.locate(state_file);
+        let pointer = raw_ptr
+            .location()
+            .cloned()
+            .unwrap_or_else(|| parent.pointer().clone());
+        let span = state_file.span(&pointer);
+        SparseLocation::new(file, pointer, span)
     }
 
     /// Get the root path of the state, if any
@@ -132,34 +744,47 @@ impl SparseState {
         self.map_raw.get_mut(path).ok_or(SparseError::NotInState)
     }
 
+    /// Get a distant document from the state
+    #[cfg(feature = "url")]
+    pub fn get_state_file_url<'a>(&'a self, url: &Url) -> Result<&'a SparseStateFile, SparseError> {
+        self.map_url.get(url).ok_or(SparseError::NotInState)
+    }
+
+    /// Get a distant document from the state (mutable)
+    #[cfg(feature = "url")]
+    pub(crate) fn get_state_file_url_mut<'a>(
+        &'a mut self,
+        url: &Url,
+    ) -> Result<&'a mut SparseStateFile, SparseError> {
+        self.map_url.get_mut(url).ok_or(SparseError::NotInState)
+    }
+
+    /// Get the document a [SparseMetadata](crate::SparseMetadata) is pointing to
+    pub(crate) fn get_state_file_for<'a>(
+        &'a self,
+        utils: &SparseMetadata,
+    ) -> Result<&'a SparseStateFile, SparseError> {
+        #[cfg(feature = "url")]
+        if let Some(url) = utils.pfile_url() {
+            return self.get_state_file_url(url);
+        }
+        self.get_state_file(utils.pfile_path())
+    }
+
     /// Deserialize the root document from the state to the type S
     pub fn parse_root<S: DeserializeOwned + Serialize + SparsableTrait>(
         &mut self,
     ) -> Result<S, SparseError> {
-        let d = self
-            .map_raw
-            .get(self.get_root_path())
-            .ok_or(SparseError::NotInState)?
-            .val()
-            .clone()
-            .into_deserializer();
-        let res: Result<S, serde_path_to_error::Error<serde_json::Error>> =
-            serde_path_to_error::deserialize(d);
-        match res {
-            Ok(mut res) => {
-                <S as SparsableTrait>::sparse_init(
-                    &mut res,
-                    self,
-                    &SparseMetadata::new(String::from("/"), self.get_root_path().clone()),
-                    0,
-                )?;
-                Ok(res)
-            }
-            Err(err) => {
-                println!("Error path : {:#?}", err.path().to_string());
-                Err(SparseError::SerdeJson(err.into_inner()))
-            }
-        }
+        let utils = SparseMetadata::new(String::from("#"), self.get_root_path().clone())?;
+        self.reset_counts();
+        let mut res: S = utils.deserialize(
+            self.map_raw
+                .get(self.get_root_path())
+                .ok_or(SparseError::NotInState)?
+                .val(),
+        )?;
+        <S as SparsableTrait>::sparse_init(&mut res, self, &utils, 0)?;
+        Ok(res)
     }
 
     /// Deserialize a document from the state to the type S
@@ -168,17 +793,17 @@ impl SparseState {
         path: PathBuf,
     ) -> Result<S, SparseError> {
         let path = SparseMetadata::normalize_path(path, self.get_root_path().clone())?;
-        let mut res: S = serde_json::from_value::<S>(
+        self.reset_counts();
+        let mut res: S = SparseMetadata::new(String::from("#"), path.clone())?.deserialize(
             self.map_raw
                 .get(&path)
                 .ok_or(SparseError::NotInState)?
-                .val()
-                .clone(),
+                .val(),
         )?;
         <S as SparsableTrait>::sparse_init(
             &mut res,
             self,
-            &SparseMetadata::new(String::from("/"), self.get_root_path().clone()),
+            &SparseMetadata::new(String::from("#"), self.get_root_path().clone())?,
             0,
         )?;
         Ok(res)
@@ -190,8 +815,9 @@ impl SparseState {
         if self.map_raw.contains_key(&path) {
             return Ok(());
         }
+        let format = SparseState::in_memory_format(&path);
         self.map_raw
-            .insert(path, SparseStateFile::new(value, SparseFileFormat::Yaml));
+            .insert(path, SparseStateFile::new(value, format));
         Ok(())
     }
 
@@ -201,17 +827,17 @@ impl SparseState {
         path: PathBuf,
         obj: &mut S,
     ) -> Result<(), SparseError> {
-        let mut obj = obj;
         let path = SparseMetadata::normalize_path(path, self.get_root_path().clone())?;
         <S as SparsableTrait>::sparse_init(
-            &mut obj,
+            obj,
             self,
-            &SparseMetadata::new(String::from("/"), self.get_root_path().clone()),
+            &SparseMetadata::new(String::from("#"), self.get_root_path().clone())?,
             0,
         )?;
+        let format = SparseState::in_memory_format(&path);
         self.map_raw.insert(
             path,
-            SparseStateFile::new(serde_json::to_value(obj)?, SparseFileFormat::Yaml),
+            SparseStateFile::new(serde_json::to_value(obj)?, format),
         );
         Ok(())
     }
@@ -225,66 +851,242 @@ impl SparseState {
         if self.map_raw.contains_key(&npath) {
             return Ok(());
         }
-        if self.in_memory {
+        if !self.distant_files {
             return Err(SparseError::NoDistantFile);
         }
-        let file = fs::File::open(npath.as_path())?;
-        let val: Value = serde_json::from_reader(file)?;
-        self.map_raw
-            .insert(npath, SparseStateFile::new(val, SparseFileFormat::Yaml));
+        self.policy.check_path(npath.as_path())?;
+        self.limits
+            .check(SparseLimit::Files, self.files_count() as u64 + 1)?;
+        let state_file = SparseState::read_file(&*self.loader, npath.as_path(), &self.limits)?;
+        self.map_raw.insert(npath, state_file);
+        Ok(())
+    }
+
+    /// Fetch a distant document using the [SparseLoader](crate::SparseLoader) and add it to the state
+    #[cfg(feature = "url")]
+    pub fn add_url(&mut self, url: Url) -> Result<(), SparseError> {
+        if self.map_url.contains_key(&url) {
+            return Ok(());
+        }
+        if !self.distant_files {
+            return Err(SparseError::NoDistantFile);
+        }
+        self.policy.check_url(&url)?;
+        self.limits
+            .check(SparseLimit::Files, self.files_count() as u64 + 1)?;
+        let (raw, format) = self.loader.load_url_limited(&url, &self.limits)?;
+        let state_file = SparseState::parse_raw(&raw, format)?;
+        self.map_url.insert(url, state_file);
         Ok(())
     }
 
-    /// Write a [SparseStateFile](crate::SparseStateFile) into the state
-    fn write_file(
-        file: &mut fs::File,
+    /// Add the document a [SparseMetadata](crate::SparseMetadata) is pointing to, if not already in the state
+    pub(crate) fn add_file_for(&mut self, utils: &SparseMetadata) -> Result<(), SparseError> {
+        #[cfg(feature = "url")]
+        if let Some(url) = utils.pfile_url() {
+            return self.add_url(url.clone());
+        }
+        if let (Some(path), _) = SparseMetadata::split_pointer(utils.raw_pointer()) {
+            self.policy.check_ref_path(path)?;
+        }
+        self.add_file(utils.pfile_path().clone())
+    }
+
+    /// Serialize a value as prettified `JSON`, using the specified indentation
+    fn to_string_indented(val: &Value, indent: &str) -> Result<String, SparseError> {
+        let mut buf: Vec<u8> = Vec::new();
+        let mut serializer = serde_json::Serializer::with_formatter(
+            &mut buf,
+            serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes()),
+        );
+        val.serialize(&mut serializer)?;
+        Ok(String::from_utf8_lossy(&buf).into_owned())
+    }
+
+    /// Serialize a [SparseStateFile](crate::SparseStateFile) in the specified format, or its own
+    ///
+    /// If it keeps its format, its original text is patched when possible. Otherwise, if
+    /// `lossless`, it fails.
+    fn serialize_file(
+        path: &Path,
         state_file: &SparseStateFile,
         format: Option<SparseFileFormat>,
-    ) -> Result<(), SparseError> {
-        match format {
-            Some(SparseFileFormat::Json(pretty)) => {
-                let val = match pretty {
-                    true => serde_json::to_string_pretty(state_file.val())?,
-                    false => serde_json::to_string(state_file.val())?,
-                };
-                file.set_len(0)?;
-                file.write_all(val.as_bytes())?;
-                file.sync_all()?;
+        lossless: bool,
+    ) -> Result<String, SparseError> {
+        let format = format.unwrap_or_else(|| state_file.ftype());
+        if let (Some(raw), true) = (state_file.raw(), format == state_file.ftype()) {
+            match sparse_patch::patch(raw, format, state_file.val()) {
+                Some(res) => return Ok(res),
+                None if lossless => {
+                    return Err(SparseError::NotPatchable(path.display().to_string()))
+                }
+                None => (),
+            }
+        }
+        let own_format = format == state_file.ftype();
+        let mut res = match (format, state_file.indent()) {
+            (SparseFileFormat::Json(true), Some(indent)) if own_format => {
+                SparseState::to_string_indented(state_file.val(), indent)?
             }
-            Some(SparseFileFormat::Yaml) => {
-                let val = serde_yaml::to_string(state_file.val())?;
-                file.set_len(0)?;
-                file.write_all(val.as_bytes())?;
-                file.sync_all()?;
+            #[cfg(feature = "json5")]
+            (SparseFileFormat::Json5, Some(indent)) if own_format => {
+                SparseState::to_string_indented(state_file.val(), indent)?
             }
-            None => {
-                SparseState::write_file(file, state_file, Some(state_file.ftype()))?;
+            (SparseFileFormat::Json(true), _) => serde_json::to_string_pretty(state_file.val())?,
+            // Prettified `JSON` is valid `JSON5`
+            #[cfg(feature = "json5")]
+            (SparseFileFormat::Json5, _) => serde_json::to_string_pretty(state_file.val())?,
+            (SparseFileFormat::Json(false), _) => serde_json::to_string(state_file.val())?,
+            (SparseFileFormat::Yaml, _) => match state_file.val() {
+                Value::Array(docs) if state_file.stream() => docs
+                    .iter()
+                    .map(serde_yaml::to_string)
+                    .collect::<Result<Vec<String>, _>>()?
+                    .concat(),
+                val => serde_yaml::to_string(val)?,
+            },
+            // The tables must be written after the values, which `toml::Value` takes care of
+            #[cfg(feature = "toml")]
+            (SparseFileFormat::Toml, _) => {
+                toml_inner::to_string(&toml_inner::Value::try_from(state_file.val())?)?
             }
+        };
+        if own_format && state_file.final_newline && !res.ends_with('\n') {
+            res.push('\n');
+        }
+        Ok(res)
+    }
+
+    /// Get the path of a temporary file next to `path`
+    fn temp_path_for(path: &Path) -> PathBuf {
+        let mut rng = rand::thread_rng();
+        let mut res = path.to_path_buf();
+        res.set_file_name(format!(
+            ".{}.{:016x}.tmp",
+            path.file_name()
+                .map(|x| x.to_string_lossy())
+                .unwrap_or_default(),
+            rng.gen::<u64>()
+        ));
+        res
+    }
+
+    /// Write a serialized document to `temp_path` and sync it, keeping the permissions of `path`
+    fn write_temp_file(path: &Path, temp_path: &Path, content: &str) -> Result<(), SparseError> {
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(temp_path)?;
+        file.write_all(content.as_bytes())?;
+        if let Ok(metadata) = fs::metadata(path) {
+            file.set_permissions(metadata.permissions())?;
         }
+        file.sync_all()?;
         Ok(())
     }
 
-    /// Write all the files in the states to disks
-    /// It'll try not to modify anything until it's sure it can open every file
-    /// for writing
-    pub fn save_to_disk(&self, format: Option<SparseFileFormat>) -> Result<(), SparseError> {
-        let mut files: Vec<(fs::File, &SparseStateFile)> = Vec::new();
+    /// Mark a file as saved, its saved text `content` being the new base of the patches,
+    /// the spans and the formatting.
+    fn saved_file(&mut self, path: &Path, content: String, format: Option<SparseFileFormat>) {
+        if let Some(state_file) = self.map_raw.get_mut(path) {
+            let ftype = format.unwrap_or(state_file.ftype);
+            match SparseState::parse_raw(content.as_bytes(), ftype) {
+                // The version is kept, as the value is the same
+                Ok(mut saved) => {
+                    saved.version = state_file.version;
+                    *state_file = saved;
+                }
+                Err(_) => {
+                    state_file.dirty = false;
+                    state_file.ftype = ftype;
+                    state_file.raw = Some(content);
+                }
+            }
+        }
+    }
 
-        for (path_buf, val) in self.map_raw.iter() {
-            let mut file: fs::File = fs::OpenOptions::new()
-                .append(true)
-                .open(path_buf.as_path())?;
-            file.seek(SeekFrom::Start(0))?;
+    /// Write the dirty files of the state to disk, returning the paths written
+    ///
+    /// Every file is serialized, then written to a temporary file next to the original
+    /// and synced. Only when all of them were written are they renamed over the originals,
+    /// so a failure up to that point leaves the files on disk untouched.
+    ///
+    /// Each file is replaced atomically, but not the files as a whole: if renaming one of
+    /// them fails, the files renamed before it stay saved and are no longer dirty, while
+    /// the others are left untouched and dirty. The files are marked as saved only once
+    /// their directories are synced, they stay dirty if it fails.
+    ///
+    /// The distant documents can't be saved: if one of them was modified, it fails with
+    /// [UnsavableUrl](crate::SparseError::UnsavableUrl) before writing anything.
+    ///
+    /// If `format` is `None`, the [output format](crate::SparseState::output_format) of the
+    /// state is used, or else the format of each document.
+    pub fn save_to_disk(
+        &mut self,
+        format: Option<SparseFileFormat>,
+    ) -> Result<Vec<PathBuf>, SparseError> {
+        #[cfg(feature = "url")]
+        if let Some(url) = self
+            .map_url
+            .iter()
+            .filter(|(_url, x)| x.dirty())
+            .map(|(url, _x)| url)
+            .min()
+        {
+            return Err(SparseError::UnsavableUrl(url.to_string()));
+        }
+        let format = format.or(self.output_format);
+        let mut files: Vec<(PathBuf, PathBuf, String)> = Vec::new();
+        for (path, state_file) in self.map_raw.iter().filter(|(_path, x)| x.dirty()) {
             files.push((
-                fs::OpenOptions::new()
-                    .append(true)
-                    .open(path_buf.as_path())?,
-                val,
+                path.clone(),
+                SparseState::temp_path_for(path),
+                SparseState::serialize_file(path, state_file, format, self.lossless)?,
             ));
         }
-        for (mut file, sparse_state_file) in files.into_iter() {
-            SparseState::write_file(&mut file, sparse_state_file, format)?;
+        files.sort();
+        if let Err(err) = files.iter().try_for_each(|(path, temp_path, content)| {
+            SparseState::write_temp_file(path, temp_path, content)
+        }) {
+            for (_path, temp_path, _content) in files.iter() {
+                let _ = fs::remove_file(temp_path);
+            }
+            return Err(err);
         }
-        Ok(())
+        let mut renamed: Vec<(PathBuf, String)> = Vec::new();
+        let mut failure: Option<SparseError> = None;
+        for (path, temp_path, content) in files.into_iter() {
+            if failure.is_some() {
+                let _ = fs::remove_file(&temp_path);
+                continue;
+            }
+            match fs::rename(&temp_path, &path) {
+                Ok(()) => renamed.push((path, content)),
+                Err(err) => {
+                    let _ = fs::remove_file(&temp_path);
+                    failure = Some(SparseError::Io(err));
+                }
+            }
+        }
+        #[cfg(unix)]
+        {
+            let parents: std::collections::BTreeSet<&Path> = renamed
+                .iter()
+                .filter_map(|(path, _content)| path.parent())
+                .collect();
+            for parent in parents.into_iter() {
+                // Persist the renames themselves
+                fs::File::open(parent)?.sync_all()?;
+            }
+        }
+        let mut written: Vec<PathBuf> = Vec::new();
+        for (path, content) in renamed.into_iter() {
+            self.saved_file(&path, content, format);
+            written.push(path);
+        }
+        if let Some(err) = failure {
+            return Err(err);
+        }
+        Ok(written)
     }
 }
@@ -0,0 +1,296 @@
+use super::*;
+use path_absolutize::*;
+use path_clean::PathClean;
+use std::io::Read;
+use std::marker::PhantomData;
+
+/// # Configure and create a [SparseState](crate::SparseState)
+///
+/// Every option has a default, so that only the relevant ones need to be set:
+///
+/// - The loader is a [SparseFileLoader](crate::SparseFileLoader)
+/// - The documents are saved in their own format, serialized again when their changes
+///   can't be patched
+/// - The max depth is [MAX_SPARSE_DEPTH](crate::MAX_SPARSE_DEPTH)
+/// - The distant files can be fetched only if the root document was read from a file,
+///   or if a loader was set
+/// - The relative paths are resolved from the current directory
+/// - There are no [SparseLimits](crate::SparseLimits) and no [SparsePolicy](crate::SparsePolicy)
+#[derive(Debug, Clone, Default)]
+pub struct SparseStateBuilder {
+    loader: Option<SparseRc<dyn SparseLoader>>,
+    output_format: Option<SparseFileFormat>,
+    lossless: bool,
+    max_depth: Option<u32>,
+    distant_files: Option<bool>,
+    base_path: Option<PathBuf>,
+    limits: SparseLimits,
+    policy: SparsePolicy,
+}
+
+impl SparseStateBuilder {
+    /// Create a new [SparseStateBuilder](crate::SparseStateBuilder) with the default options
+    pub fn new() -> Self {
+        SparseStateBuilder::default()
+    }
+
+    /// Fetch the documents using `loader`
+    pub fn loader(mut self, loader: SparseRc<dyn SparseLoader>) -> Self {
+        self.loader = Some(loader);
+        self
+    }
+
+    /// Save the documents in `format` when no format is specified when saving
+    pub fn output_format(mut self, format: SparseFileFormat) -> Self {
+        self.output_format = Some(format);
+        self
+    }
+
+    /// Require the documents saved in their own format to be saved by patching their
+    /// original text, see [SparseState::set_lossless](crate::SparseState::set_lossless)
+    pub fn lossless(mut self, lossless: bool) -> Self {
+        self.lossless = lossless;
+        self
+    }
+
+    /// Set the max stack frames the resolution of a document can go
+    pub fn max_depth(mut self, max_depth: u32) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Allow, or deny, fetching the documents that are not in the state
+    pub fn distant_files(mut self, allow: bool) -> Self {
+        self.distant_files = Some(allow);
+        self
+    }
+
+    /// Resolve the relative paths of the root documents from the directory `base_path`
+    pub fn base_path(mut self, base_path: PathBuf) -> Self {
+        self.base_path = Some(base_path);
+        self
+    }
+
+    /// Limit the resources the state can use
+    pub fn limits(mut self, limits: SparseLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Restrict the documents the state can fetch
+    pub fn policy(mut self, policy: SparsePolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Resolve the path of the root document from the base path
+    fn resolve_path(&self, path: PathBuf) -> Result<PathBuf, SparseError> {
+        let base_path = match &self.base_path {
+            Some(base_path) => base_path.clone(),
+            None => std::env::current_dir()?,
+        };
+        Ok(base_path.join(path).absolutize()?.to_path_buf().clean())
+    }
+
+    /// Create the state around its root document
+    fn finish(self, path: PathBuf, root: SparseStateFile, distant_files: bool) -> SparseState {
+        let distant_files = self
+            .distant_files
+            .unwrap_or(distant_files || self.loader.is_some());
+        let loader = self
+            .loader
+            .unwrap_or_else(|| SparseRc::new(SparseFileLoader));
+        let mut res = SparseState::new_with_root(path, root, loader, distant_files);
+        res.set_output_format(self.output_format);
+        res.set_lossless(self.lossless);
+        res.set_max_depth(self.max_depth.unwrap_or(MAX_SPARSE_DEPTH));
+        res.set_limits(self.limits);
+        res.set_policy(self.policy);
+        res
+    }
+
+    /// Create the state, reading the root document from `path` using the loader
+    pub fn build_from_file(self, path: PathBuf) -> Result<SparseState, SparseError> {
+        let path = self.resolve_path(path)?;
+        self.policy.check_path(path.as_path())?;
+        let root = match &self.loader {
+            Some(loader) => SparseState::read_file(&**loader, path.as_path(), &self.limits)?,
+            None => SparseState::read_file(&SparseFileLoader, path.as_path(), &self.limits)?,
+        };
+        Ok(self.finish(path, root, true))
+    }
+
+    /// Create the state from the root document `val`, living at the virtual path `path`.
+    ///
+    /// As it doesn't originate from the disk, the root document is dirty: the first
+    /// [save_to_disk](crate::SparseState::save_to_disk) writes it at `path`.
+    pub fn build_from_value(self, path: PathBuf, val: Value) -> Result<SparseState, SparseError> {
+        let path = self.resolve_path(path)?;
+        let format = SparseFileFormat::from_path(&path).unwrap_or(SparseFileFormat::Json(true));
+        Ok(self.finish(path, SparseStateFile::new(val, format), false))
+    }
+
+    /// Create the state from the raw root document `raw`, living at the virtual path `path`.
+    ///
+    /// If `format` is `None`, it's detected from `path` then `raw`.
+    ///
+    /// As it doesn't originate from the disk, the root document is dirty: the first
+    /// [save_to_disk](crate::SparseState::save_to_disk) writes it at `path`.
+    pub fn build_from_slice(
+        self,
+        path: PathBuf,
+        raw: &[u8],
+        format: Option<SparseFileFormat>,
+    ) -> Result<SparseState, SparseError> {
+        let path = self.resolve_path(path)?;
+        self.limits.check(SparseLimit::FileSize, raw.len() as u64)?;
+        let format = match format {
+            Some(format) => format,
+            None => SparseFileFormat::detect(&path, raw)?,
+        };
+        let mut root = SparseState::parse_raw(raw, format)?;
+        root.set_dirty();
+        Ok(self.finish(path, root, false))
+    }
+
+    /// Create the state from the raw root document `raw`, living at the virtual path `path`.
+    ///
+    /// See [build_from_slice](crate::SparseStateBuilder::build_from_slice)
+    pub fn build_from_str(
+        self,
+        path: PathBuf,
+        raw: &str,
+        format: Option<SparseFileFormat>,
+    ) -> Result<SparseState, SparseError> {
+        self.build_from_slice(path, raw.as_bytes(), format)
+    }
+
+    /// Create the state from the root document read from `reader`, living at the virtual path `path`.
+    ///
+    /// See [build_from_slice](crate::SparseStateBuilder::build_from_slice)
+    pub fn build_from_reader<R: Read>(
+        self,
+        path: PathBuf,
+        reader: R,
+        format: Option<SparseFileFormat>,
+    ) -> Result<SparseState, SparseError> {
+        let raw = self.limits.read_to_end(reader)?;
+        self.build_from_slice(path, &raw, format)
+    }
+}
+
+/// # Configure and create a [SparseRoot](crate::SparseRoot)
+///
+/// It's a [SparseStateBuilder](crate::SparseStateBuilder), whose state is then used
+/// to parse the root document.
+#[derive(Debug)]
+pub struct SparseRootBuilder<S> {
+    state: SparseStateBuilder,
+    _root: PhantomData<S>,
+}
+
+impl<S> Default for SparseRootBuilder<S> {
+    fn default() -> Self {
+        SparseRootBuilder {
+            state: SparseStateBuilder::default(),
+            _root: PhantomData,
+        }
+    }
+}
+
+impl<S> SparseRootBuilder<S>
+where
+    S: DeserializeOwned + Serialize + SparsableTrait,
+{
+    /// Create a new [SparseRootBuilder](crate::SparseRootBuilder) with the default options
+    pub fn new() -> Self {
+        SparseRootBuilder::default()
+    }
+
+    /// Configure the state, see [SparseStateBuilder::loader](crate::SparseStateBuilder::loader)
+    pub fn loader(self, loader: SparseRc<dyn SparseLoader>) -> Self {
+        self.map(|state| state.loader(loader))
+    }
+
+    /// Configure the state, see [SparseStateBuilder::output_format](crate::SparseStateBuilder::output_format)
+    pub fn output_format(self, format: SparseFileFormat) -> Self {
+        self.map(|state| state.output_format(format))
+    }
+
+    /// Configure the state, see [SparseStateBuilder::lossless](crate::SparseStateBuilder::lossless)
+    pub fn lossless(self, lossless: bool) -> Self {
+        self.map(|state| state.lossless(lossless))
+    }
+
+    /// Configure the state, see [SparseStateBuilder::max_depth](crate::SparseStateBuilder::max_depth)
+    pub fn max_depth(self, max_depth: u32) -> Self {
+        self.map(|state| state.max_depth(max_depth))
+    }
+
+    /// Configure the state, see [SparseStateBuilder::distant_files](crate::SparseStateBuilder::distant_files)
+    pub fn distant_files(self, allow: bool) -> Self {
+        self.map(|state| state.distant_files(allow))
+    }
+
+    /// Configure the state, see [SparseStateBuilder::base_path](crate::SparseStateBuilder::base_path)
+    pub fn base_path(self, base_path: PathBuf) -> Self {
+        self.map(|state| state.base_path(base_path))
+    }
+
+    /// Configure the state, see [SparseStateBuilder::limits](crate::SparseStateBuilder::limits)
+    pub fn limits(self, limits: SparseLimits) -> Self {
+        self.map(|state| state.limits(limits))
+    }
+
+    /// Configure the state, see [SparseStateBuilder::policy](crate::SparseStateBuilder::policy)
+    pub fn policy(self, policy: SparsePolicy) -> Self {
+        self.map(|state| state.policy(policy))
+    }
+
+    fn map(self, f: impl FnOnce(SparseStateBuilder) -> SparseStateBuilder) -> Self {
+        SparseRootBuilder {
+            state: f(self.state),
+            _root: PhantomData,
+        }
+    }
+
+    /// Create the root, reading the root document from `path`
+    pub fn build_from_file(self, path: PathBuf) -> Result<SparseRoot<S>, SparseError> {
+        SparseRoot::new_from_state(self.state.build_from_file(path)?)
+    }
+
+    /// Create the root from the root document `val`, living at the virtual path `path`
+    pub fn build_from_value(self, path: PathBuf, val: Value) -> Result<SparseRoot<S>, SparseError> {
+        SparseRoot::new_from_state(self.state.build_from_value(path, val)?)
+    }
+
+    /// Create the root from the raw root document `raw`, living at the virtual path `path`
+    pub fn build_from_slice(
+        self,
+        path: PathBuf,
+        raw: &[u8],
+        format: Option<SparseFileFormat>,
+    ) -> Result<SparseRoot<S>, SparseError> {
+        SparseRoot::new_from_state(self.state.build_from_slice(path, raw, format)?)
+    }
+
+    /// Create the root from the raw root document `raw`, living at the virtual path `path`
+    pub fn build_from_str(
+        self,
+        path: PathBuf,
+        raw: &str,
+        format: Option<SparseFileFormat>,
+    ) -> Result<SparseRoot<S>, SparseError> {
+        SparseRoot::new_from_state(self.state.build_from_str(path, raw, format)?)
+    }
+
+    /// Create the root from the root document read from `reader`, living at the virtual path `path`
+    pub fn build_from_reader<R: Read>(
+        self,
+        path: PathBuf,
+        reader: R,
+        format: Option<SparseFileFormat>,
+    ) -> Result<SparseRoot<S>, SparseError> {
+        SparseRoot::new_from_state(self.state.build_from_reader(path, reader, format)?)
+    }
+}
@@ -0,0 +1,118 @@
+use super::*;
+
+#[cfg(not(feature = "sync"))]
+use std::cell::{Ref, RefCell, RefMut};
+#[cfg(not(feature = "sync"))]
+use std::rc::Rc;
+#[cfg(feature = "sync")]
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// The reference counted pointer used by [Sparse](crate).
+///
+/// It's an [Rc](std::rc::Rc), or an [Arc](std::sync::Arc) with the `sync` feature.
+#[cfg(not(feature = "sync"))]
+pub type SparseRc<T> = Rc<T>;
+/// The reference counted pointer used by [Sparse](crate).
+///
+/// It's an [Rc](std::rc::Rc), or an [Arc](std::sync::Arc) with the `sync` feature.
+#[cfg(feature = "sync")]
+pub type SparseRc<T> = Arc<T>;
+
+/// The weak counterpart of [SparseRc](crate::SparseRc)
+#[cfg(not(feature = "sync"))]
+pub(crate) type SparseWeak<T> = std::rc::Weak<T>;
+/// The weak counterpart of [SparseRc](crate::SparseRc)
+#[cfg(feature = "sync")]
+pub(crate) type SparseWeak<T> = std::sync::Weak<T>;
+
+/// The cell in which the [SparseState](crate::SparseState) is shared.
+///
+/// It's a [RefCell](std::cell::RefCell), or a [RwLock](std::sync::RwLock) with the `sync` feature.
+#[cfg(not(feature = "sync"))]
+pub type SparseCell<T> = RefCell<T>;
+/// The cell in which the [SparseState](crate::SparseState) is shared.
+///
+/// It's a [RefCell](std::cell::RefCell), or a [RwLock](std::sync::RwLock) with the `sync` feature.
+#[cfg(feature = "sync")]
+pub type SparseCell<T> = RwLock<T>;
+
+/// A borrowed [SparseState](crate::SparseState)
+#[cfg(not(feature = "sync"))]
+pub type SparseStateRef<'a> = Ref<'a, SparseState>;
+/// A borrowed [SparseState](crate::SparseState)
+#[cfg(feature = "sync")]
+pub type SparseStateRef<'a> = RwLockReadGuard<'a, SparseState>;
+
+/// A mutably borrowed [SparseState](crate::SparseState)
+#[cfg(not(feature = "sync"))]
+pub type SparseStateRefMut<'a> = RefMut<'a, SparseState>;
+/// A mutably borrowed [SparseState](crate::SparseState)
+#[cfg(feature = "sync")]
+pub type SparseStateRefMut<'a> = RwLockWriteGuard<'a, SparseState>;
+
+/// The shared [SparseState](crate::SparseState), as held by a [SparseRoot](crate::SparseRoot)
+pub type SparseStateCell = SparseRc<SparseCell<SparseState>>;
+
+/// The once-initialized cell used by [SparseRefLazy](crate::SparseRefLazy)
+#[cfg(not(feature = "sync"))]
+pub(crate) type SparseOnceCell<T> = std::cell::OnceCell<T>;
+/// The once-initialized cell used by [SparseRefLazy](crate::SparseRefLazy)
+#[cfg(feature = "sync")]
+pub(crate) type SparseOnceCell<T> = std::sync::OnceLock<T>;
+
+/// A type-erased value shared in the [SparseState](crate::SparseState)
+#[cfg(not(feature = "sync"))]
+pub(crate) type SparseAnyRc = Rc<dyn std::any::Any>;
+/// A type-erased value shared in the [SparseState](crate::SparseState)
+#[cfg(feature = "sync")]
+pub(crate) type SparseAnyRc = Arc<dyn std::any::Any + Send + Sync>;
+
+/// # Bounds required to share a value between threads
+///
+/// Without the `sync` feature, every type implements it. With the `sync` feature,
+/// only the types that are `Send + Sync` do.
+#[cfg(not(feature = "sync"))]
+pub trait SparseSendSync {}
+#[cfg(not(feature = "sync"))]
+impl<T: ?Sized> SparseSendSync for T {}
+
+/// # Bounds required to share a value between threads
+///
+/// Without the `sync` feature, every type implements it. With the `sync` feature,
+/// only the types that are `Send + Sync` do.
+#[cfg(feature = "sync")]
+pub trait SparseSendSync: Send + Sync {}
+#[cfg(feature = "sync")]
+impl<T: ?Sized + Send + Sync> SparseSendSync for T {}
+
+/// # Borrow the [SparseState](crate::SparseState) from its [SparseCell](crate::SparseCell)
+pub trait SparseStateBorrow {
+    /// Borrow the state
+    fn try_borrow_state(&self) -> Result<SparseStateRef<'_>, SparseError>;
+    /// Borrow the state mutably
+    fn try_borrow_state_mut(&self) -> Result<SparseStateRefMut<'_>, SparseError>;
+}
+
+#[cfg(not(feature = "sync"))]
+impl SparseStateBorrow for SparseCell<SparseState> {
+    fn try_borrow_state(&self) -> Result<SparseStateRef<'_>, SparseError> {
+        self.try_borrow()
+            .map_err(|_e| SparseError::StateAlreadyBorrowed)
+    }
+
+    fn try_borrow_state_mut(&self) -> Result<SparseStateRefMut<'_>, SparseError> {
+        self.try_borrow_mut()
+            .map_err(|_e| SparseError::StateAlreadyBorrowed)
+    }
+}
+
+#[cfg(feature = "sync")]
+impl SparseStateBorrow for SparseCell<SparseState> {
+    fn try_borrow_state(&self) -> Result<SparseStateRef<'_>, SparseError> {
+        self.read().map_err(|_e| SparseError::StatePoisoned)
+    }
+
+    fn try_borrow_state_mut(&self) -> Result<SparseStateRefMut<'_>, SparseError> {
+        self.write().map_err(|_e| SparseError::StatePoisoned)
+    }
+}
@@ -1,5 +1,4 @@
 use super::*;
-use std::cell::RefMut;
 use std::fmt::{self, Display};
 use std::ops::Deref;
 
@@ -38,31 +37,29 @@ where
     pub fn try_deref_raw_pointer<T: 'static + DeserializeOwned + Serialize + SparsableTrait>(
         curr: &SparseValue<'_, S>,
         ptr: String,
-        state_cell: Rc<RefCell<SparseState>>,
+        state_cell: SparseStateCell,
     ) -> Result<SparseSelector<T>, SparseError> {
-        let current_path: Option<&PathBuf> = curr.metadata().map(|v| v.pfile_path());
         let (mut val, metadata): (SparseSelector<T>, SparseMetadata) = {
-            let mut state_mut: RefMut<'_, SparseState> = state_cell
-                .try_borrow_mut()
-                .map_err(|_e| SparseError::StateAlreadyBorrowed)?;
-            let metadata = SparseMetadata::new(
-                ptr.clone(),
-                current_path
-                    .unwrap_or_else(|| state_mut.get_root_path())
-                    .clone(),
-            );
+            let mut state_mut: SparseStateRefMut<'_> = state_cell.try_borrow_state_mut()?;
+            let metadata = match curr.metadata() {
+                Some(parent) => SparseMetadata::new_from_parent(ptr, parent)?,
+                None => SparseMetadata::new(ptr, state_mut.get_root_path().clone())?,
+            };
             let sref: SparseRef<T> =
-                SparseRef::new(&mut *state_mut, metadata.pfile_path().clone(), ptr, 0)?;
+                SparseRef::new_from_metadata(&mut state_mut, metadata.clone(), 0)?;
             (SparseSelector::Obj(SparsePointedValue::Ref(sref)), metadata)
         };
-        let mut state_mut: RefMut<'_, SparseState> = state_cell
-            .try_borrow_mut()
-            .map_err(|_e| SparseError::StateAlreadyBorrowed)?;
-        val.sparse_init(&mut *state_mut, &metadata, 0)?;
+        let mut state_mut: SparseStateRefMut<'_> = state_cell.try_borrow_state_mut()?;
+        val.sparse_init(&mut state_mut, &metadata, 0)?;
         Ok(val)
     }
 
     pub(crate) fn new(sref: &'a S, metadata: Option<&'a SparseMetadata>) -> Self {
         SparseValue { sref, metadata }
     }
+
+    /// The value, borrowed for as long as its owner
+    pub(crate) fn sref(&self) -> &'a S {
+        self.sref
+    }
 }
@@ -1,8 +1,10 @@
 use super::*;
-use std::cell::{Ref, RefMut};
 use std::fmt::{self, Display};
 use std::ops::{Deref, DerefMut};
 
+#[cfg(feature = "url")]
+use url_inner::Url;
+
 /// # A value extracted from a [SparsePointer](crate::SparsePointer) (mutable)
 #[derive(Debug, Getters, CopyGetters, MutGetters)]
 pub struct SparseValueMut<'a, S> {
@@ -10,9 +12,12 @@ pub struct SparseValueMut<'a, S> {
     version: Option<u64>,
     #[getset(get = "pub")]
     path: Option<PathBuf>,
+    #[cfg(feature = "url")]
+    #[getset(get = "pub")]
+    url: Option<Url>,
     #[getset(get = "pub")]
-    pointer: Option<&'a String>,
-    state_cell: Rc<RefCell<SparseState>>,
+    pointer: Option<&'a JsonPointer>,
+    state_cell: SparseStateCell,
     sref: &'a mut S,
 }
 
@@ -41,7 +46,7 @@ where
     S: DeserializeOwned + Serialize + SparsableTrait,
 {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.sref
+        self.sref
     }
 }
 
@@ -52,39 +57,38 @@ where
     pub fn try_deref_raw_pointer<T: 'static + DeserializeOwned + Serialize + SparsableTrait>(
         curr: &SparseValueMut<'_, S>,
         ptr: String,
-        state_cell: Rc<RefCell<SparseState>>,
+        state_cell: SparseStateCell,
     ) -> Result<SparseSelector<T>, SparseError> {
         let current_path: Option<&PathBuf> = curr.path().as_ref();
         let (mut val, metadata): (SparseSelector<T>, SparseMetadata) = {
-            let mut state_mut: RefMut<'_, SparseState> = state_cell
-                .try_borrow_mut()
-                .map_err(|_e| SparseError::StateAlreadyBorrowed)?;
-            let metadata = SparseMetadata::new(
-                ptr.clone(),
-                current_path
-                    .unwrap_or_else(|| state_mut.get_root_path())
-                    .clone(),
-            );
+            let mut state_mut: SparseStateRefMut<'_> = state_cell.try_borrow_state_mut()?;
+            let base_path = current_path
+                .unwrap_or_else(|| state_mut.get_root_path())
+                .clone();
+            #[cfg(feature = "url")]
+            let metadata = SparseMetadata::new_with_url(ptr, base_path, curr.url().as_ref())?;
+            #[cfg(not(feature = "url"))]
+            let metadata = SparseMetadata::new(ptr, base_path)?;
             let sref: SparseRef<T> =
-                SparseRef::new(&mut *state_mut, metadata.pfile_path().clone(), ptr, 0)?;
+                SparseRef::new_from_metadata(&mut state_mut, metadata.clone(), 0)?;
             (SparseSelector::Obj(SparsePointedValue::Ref(sref)), metadata)
         };
-        let mut state_mut: RefMut<'_, SparseState> = state_cell
-            .try_borrow_mut()
-            .map_err(|_e| SparseError::StateAlreadyBorrowed)?;
-        val.sparse_init(&mut *state_mut, &metadata, 0)?;
+        let mut state_mut: SparseStateRefMut<'_> = state_cell.try_borrow_state_mut()?;
+        val.sparse_init(&mut state_mut, &metadata, 0)?;
         Ok(val)
     }
 
     pub(crate) fn new(
         sref: &'a mut S,
-        state_cell: Rc<RefCell<SparseState>>,
+        state_cell: SparseStateCell,
         metadata: Option<&'a SparseMetadata>,
     ) -> Self {
         SparseValueMut {
             sref,
             version: metadata.map(|x| x.version()),
             path: metadata.map(|x| x.pfile_path()).cloned(),
+            #[cfg(feature = "url")]
+            url: metadata.and_then(|x| x.pfile_url().clone()),
             pointer: metadata.map(|x| x.pointer()),
             state_cell,
         }
@@ -92,12 +96,10 @@ where
 
     pub(crate) fn new_root(
         sref: &'a mut S,
-        state_cell: Rc<RefCell<SparseState>>,
+        state_cell: SparseStateCell,
     ) -> Result<Self, SparseError> {
         let (path, version) = {
-            let state: Ref<'_, SparseState> = state_cell
-                .try_borrow()
-                .map_err(|_e| SparseError::StateAlreadyBorrowed)?;
+            let state: SparseStateRef<'_> = state_cell.try_borrow_state()?;
             let root_path = state.get_root_path();
             let root_file = state.get_state_file(root_path)?;
 
@@ -108,6 +110,8 @@ where
             sref,
             version: Some(version),
             path: Some(path),
+            #[cfg(feature = "url")]
+            url: None,
             pointer: None,
             state_cell,
         })
@@ -117,25 +121,24 @@ where
     /// One should call `sparse_updt` on the root after saving something in the state.
     pub fn sparse_save(&self) -> Result<(), SparseError> {
         let file_path: PathBuf = {
-            let state = self
-                .state_cell
-                .try_borrow()
-                .map_err(|_e| SparseError::StateAlreadyBorrowed)?;
+            let state = self.state_cell.try_borrow_state()?;
             self.path
                 .as_ref()
                 .cloned()
                 .unwrap_or_else(|| state.get_root_path().clone())
         };
-        let mut state = self
-            .state_cell
-            .try_borrow_mut()
-            .map_err(|_e| SparseError::StateAlreadyBorrowed)?;
+        let mut state = self.state_cell.try_borrow_state_mut()?;
+        #[cfg(feature = "url")]
+        let file: &mut SparseStateFile = match &self.url {
+            Some(url) => state.get_state_file_url_mut(url)?,
+            None => state.get_state_file_mut(&file_path)?,
+        };
+        #[cfg(not(feature = "url"))]
         let file: &mut SparseStateFile = state.get_state_file_mut(&file_path)?;
         match self.pointer {
             Some(pointer) => {
-                let pointed_value = file
-                    .val_mut()
-                    .pointer_mut(&pointer)
+                let pointed_value = pointer
+                    .get_mut(file.val_mut())
                     .ok_or_else(|| SparseError::UnkownPath(pointer.to_string()))?;
                 *pointed_value = serde_json::to_value(&self.sref)?;
             }
@@ -0,0 +1,111 @@
+use super::*;
+
+const ROOT_DOC: &str = "hello: world\nkey1:\n  $ref: \"other.yaml#/greeting\"\n";
+
+fn setup() -> tempfile::TempDir {
+    let temp_dir = tempfile::tempdir().unwrap(); // Setting up temp dir
+    std::fs::write(temp_dir.path().join("root.yaml"), ROOT_DOC).unwrap();
+    std::fs::write(temp_dir.path().join("other.yaml"), "greeting: universe\n").unwrap();
+    temp_dir
+}
+
+#[test]
+fn state_from_file() {
+    let temp_dir = setup();
+    let state = SparseState::builder()
+        .base_path(temp_dir.path().to_path_buf())
+        .max_depth(5)
+        .output_format(SparseFileFormat::Json(false))
+        .build_from_file(PathBuf::from("root.yaml"))
+        .unwrap();
+
+    assert_eq!(
+        state.get_root_path(),
+        &temp_dir.path().join("root.yaml"),
+        "The root path should be resolved from the base path"
+    );
+    assert_eq!(state.max_depth(), 5, "Max depth mismatch");
+    assert!(state.distant_files(), "A file can reference distant files");
+    assert_eq!(
+        state.output_format(),
+        Some(SparseFileFormat::Json(false)),
+        "Output format mismatch"
+    );
+}
+
+#[test]
+fn root_from_str() {
+    let temp_dir = setup();
+    let root: SparseRoot<SimpleStruct1> = SparseRoot::builder()
+        .base_path(temp_dir.path().to_path_buf())
+        .distant_files(true)
+        .build_from_str(PathBuf::from("virtual.yaml"), ROOT_DOC, None)
+        .unwrap();
+
+    assert_eq!(
+        *root.root_get().unwrap().key1().get().unwrap(),
+        "universe",
+        "The distant file should be resolved from the base path"
+    );
+    let state = root.state().try_borrow_state().unwrap();
+    assert_eq!(
+        state.get_state_file(state.get_root_path()).unwrap().ftype(),
+        SparseFileFormat::Yaml,
+        "The format should be detected"
+    );
+}
+
+#[test]
+fn root_from_reader_no_distant_files() {
+    let temp_dir = setup();
+    let err = SparseRoot::<SimpleStruct1>::builder()
+        .base_path(temp_dir.path().to_path_buf())
+        .build_from_reader(PathBuf::from("virtual"), ROOT_DOC.as_bytes(), None)
+        .expect_err("The distant files are denied by default");
+
+    match err {
+        SparseError::BrokenRef { source, .. } => match *source {
+            SparseError::NoDistantFile => (),
+            _ => panic!("Expected the source to be `NoDistantFile`"),
+        },
+        _ => panic!("Expected the err to be `BrokenRef`"),
+    }
+}
+
+#[test]
+fn root_limits() {
+    let temp_dir = setup();
+    let err = SparseRoot::<SimpleStruct1>::builder()
+        .limits(SparseLimits::new().limit_file_size(8))
+        .build_from_file(temp_dir.path().join("root.yaml"))
+        .expect_err("The root document is too big");
+
+    match err {
+        SparseError::LimitExceeded {
+            limit: SparseLimit::FileSize,
+            max: 8,
+        } => (),
+        _ => panic!("Expected the err to be `LimitExceeded`"),
+    }
+}
+
+#[test]
+fn save_output_format() {
+    let temp_dir = setup();
+    let path = temp_dir.path().join("other.yaml");
+    let mut state = SparseState::builder()
+        .output_format(SparseFileFormat::Json(false))
+        .build_from_file(path.clone())
+        .unwrap();
+
+    state
+        .get_state_file_mut(&path)
+        .unwrap()
+        .replace(serde_json::json!({"greeting": "galaxy"}));
+    state.save_to_disk(None).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "{\"greeting\":\"galaxy\"}",
+        "The file should be saved in the output format"
+    );
+}
@@ -0,0 +1,100 @@
+use super::*;
+use serde_json::json;
+use std::str::FromStr;
+
+#[test]
+fn bundle_distant() {
+    let root: SparseRoot<SimpleStruct3> = SparseRoot::new_from_file(
+        PathBuf::from_str(sparse_test_rel_path!("./src/tests/docs/list.json")).unwrap(),
+    )
+    .unwrap();
+
+    let bundled = root.bundle().unwrap();
+
+    assert_eq!(
+        bundled,
+        json!({
+            "list": ["world", "universe"],
+            "key1": { "$ref": "#/list/1" },
+            "key2": { "$ref": "#/definitions/simple/hello" },
+            "key3": { "$ref": "#/definitions/simple/key1" },
+            "definitions": {
+                "simple": {
+                    "hello": "world",
+                    "key1": { "$ref": "#/definitions/simple/hello" }
+                }
+            }
+        }),
+        "The bundled document doesn't match"
+    );
+    let bundled_root: SparseRoot<SimpleStruct3> =
+        SparseRoot::new_from_value(bundled, PathBuf::from("bundled.json"), vec![]).unwrap();
+    assert_eq!(
+        *bundled_root.root_get().unwrap().key3.get().unwrap(),
+        "world",
+        "The bundled document should resolve"
+    );
+}
+
+#[test]
+fn bundle_name_collision() {
+    let mut state = SparseState::new_from_value(
+        PathBuf::from("/virtual/root.json"),
+        json!({
+            "a": { "$ref": "./a/hello.json#/key" },
+            "b": { "$ref": "./b/hello.json#/~1escaped~0key" },
+            "definitions": {
+                "hello": "existing"
+            }
+        }),
+    )
+    .unwrap();
+    state
+        .add_value(
+            PathBuf::from("/virtual/a/hello.json"),
+            json!({ "key": { "$ref": "../root.json#/definitions/hello" } }),
+        )
+        .unwrap();
+    state
+        .add_value(
+            PathBuf::from("/virtual/b/hello.json"),
+            json!({ "/escaped~key": "b" }),
+        )
+        .unwrap();
+
+    let bundled = state.bundle().unwrap();
+
+    assert_eq!(
+        bundled,
+        json!({
+            "a": { "$ref": "#/definitions/hello_1/key" },
+            "b": { "$ref": "#/definitions/hello_2/~1escaped~0key" },
+            "definitions": {
+                "hello": "existing",
+                "hello_1": { "key": { "$ref": "#/definitions/hello" } },
+                "hello_2": { "/escaped~key": "b" }
+            }
+        }),
+        "The bundled document doesn't match"
+    );
+}
+
+#[test]
+fn bundle_invalid_section() {
+    let mut state = SparseState::new_from_value(
+        PathBuf::from("/virtual/root.json"),
+        json!({
+            "a": { "$ref": "./hello.json#/key" },
+            "definitions": "hello"
+        }),
+    )
+    .unwrap();
+    state
+        .add_value(PathBuf::from("/virtual/hello.json"), json!({ "key": "a" }))
+        .unwrap();
+
+    match state.bundle().expect_err("The section isn't an object") {
+        SparseError::InvalidBundleSection(_) => (),
+        _ => panic!("Expected the err to be `InvalidBundleSection`"),
+    }
+}
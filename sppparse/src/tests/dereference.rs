@@ -0,0 +1,154 @@
+use super::*;
+use serde_json::json;
+use std::str::FromStr;
+
+fn cyclic_state() -> SparseState {
+    SparseState::new_from_value(
+        PathBuf::from("/virtual/root.json"),
+        json!({
+            "node": {
+                "name": "hello",
+                "child": { "$ref": "#/node" }
+            },
+            "root": { "$ref": "#/node" }
+        }),
+    )
+    .unwrap()
+}
+
+#[test]
+fn dereference_distant() {
+    let root: SparseRoot<SimpleStruct3> = SparseRoot::new_from_file(
+        PathBuf::from_str(sparse_test_rel_path!("./src/tests/docs/list.json")).unwrap(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        root.to_dereferenced_value(SparseCycleBehavior::Error)
+            .unwrap(),
+        json!({
+            "list": ["world", "universe"],
+            "key1": "universe",
+            "key2": "world",
+            "key3": "world"
+        }),
+        "The dereferenced document doesn't match"
+    );
+}
+
+#[test]
+fn dereference_cycle_error() {
+    match cyclic_state()
+        .to_dereferenced_value(SparseCycleBehavior::Error)
+        .expect_err("The document is cyclic")
+    {
+        SparseError::CyclicRef(_) => (),
+        _ => panic!("Expected the err to be `CyclicRef`"),
+    }
+}
+
+#[test]
+fn dereference_cycle_keep_ref() {
+    assert_eq!(
+        cyclic_state()
+            .to_dereferenced_value(SparseCycleBehavior::KeepRef)
+            .unwrap(),
+        json!({
+            "node": {
+                "name": "hello",
+                "child": {
+                    "name": "hello",
+                    "child": { "$ref": "#/node/child" }
+                }
+            },
+            "root": {
+                "name": "hello",
+                "child": { "$ref": "#/root" }
+            }
+        }),
+        "The dereferenced document doesn't match"
+    );
+}
+
+#[test]
+fn dereference_cycle_cut_off() {
+    assert_eq!(
+        cyclic_state()
+            .to_dereferenced_value(SparseCycleBehavior::CutOff(2))
+            .unwrap(),
+        json!({
+            "node": {
+                "name": "hello",
+                "child": {
+                    "name": "hello",
+                    "child": {
+                        "name": "hello",
+                        "child": { "$ref": "#/node" }
+                    }
+                }
+            },
+            "root": {
+                "name": "hello",
+                "child": {
+                    "name": "hello",
+                    "child": { "$ref": "#/node" }
+                }
+            }
+        }),
+        "The dereferenced document doesn't match"
+    );
+}
+
+#[test]
+fn dereference_inline() {
+    let root: SparseRoot<SimpleStructInline3> = SparseRoot::new_from_value(
+        json!({
+            "list": ["world", "universe", "#/list/0"],
+            "key1": { "$ref": "#/list/0" },
+            "key2": "#/list/1",
+            "key3": "#/key1"
+        }),
+        PathBuf::from_str("hello.json").unwrap(),
+        vec![],
+    )
+    .unwrap();
+
+    assert_eq!(
+        root.to_dereferenced_value(SparseCycleBehavior::Error)
+            .unwrap(),
+        json!({
+            "list": ["world", "universe", "#/list/0"],
+            "key1": "world",
+            "key2": "universe",
+            "key3": "world"
+        }),
+        "The inline references should be dereferenced, not the other strings"
+    );
+}
+
+#[test]
+fn dereference_inline_after_plain_string() {
+    let root: SparseRoot<SimpleStructInline3> = SparseRoot::new_from_value(
+        json!({
+            "list": ["#/list/1", "universe"],
+            "key1": "galaxy",
+            "key2": "#/list/1",
+            "key3": "#/list/1"
+        }),
+        PathBuf::from_str("hello.json").unwrap(),
+        vec![],
+    )
+    .unwrap();
+
+    assert_eq!(
+        root.to_dereferenced_value(SparseCycleBehavior::Error)
+            .unwrap(),
+        json!({
+            "list": ["#/list/1", "universe"],
+            "key1": "galaxy",
+            "key2": "universe",
+            "key3": "universe"
+        }),
+        "The plain string equal to the inline references should be left as is"
+    );
+}
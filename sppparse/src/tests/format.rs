@@ -0,0 +1,93 @@
+use super::*;
+use std::path::Path;
+
+#[test]
+fn from_path() {
+    assert_eq!(
+        SparseFileFormat::from_path(Path::new("/a/doc.json")),
+        Some(SparseFileFormat::Json(true))
+    );
+    assert_eq!(
+        SparseFileFormat::from_path(Path::new("/a/doc.YML")),
+        Some(SparseFileFormat::Yaml)
+    );
+    assert_eq!(
+        SparseFileFormat::from_path(Path::new("/a/doc.yaml")),
+        Some(SparseFileFormat::Yaml)
+    );
+    assert_eq!(SparseFileFormat::from_path(Path::new("/a/doc")), None);
+}
+
+#[test]
+fn detect() {
+    assert_eq!(
+        SparseFileFormat::detect(Path::new("doc.json"), b"{\"hello\": \"world\"}\n").unwrap(),
+        SparseFileFormat::Json(false),
+        "A single line JSON document isn't prettified"
+    );
+    assert_eq!(
+        SparseFileFormat::detect(Path::new("doc.json"), b"{\n  \"hello\": \"world\"\n}").unwrap(),
+        SparseFileFormat::Json(true),
+    );
+    assert_eq!(
+        SparseFileFormat::detect(Path::new("doc.yaml"), b"{\"hello\": \"world\"}").unwrap(),
+        SparseFileFormat::Yaml,
+        "The extension should prevail over the content"
+    );
+    assert_eq!(
+        SparseFileFormat::detect(Path::new("doc.txt"), b"hello: world\n").unwrap(),
+        SparseFileFormat::Yaml,
+        "The content should be used for unknown extensions"
+    );
+}
+
+#[test]
+fn distant_yaml() {
+    let temp_dir = tempfile::tempdir().unwrap(); // Setting up temp dir
+    let path_file_1 = temp_dir.path().join("file.json");
+    let path_file_2 = temp_dir.path().join("file2.yml");
+    std::fs::write(
+        &path_file_1,
+        "{\n    \"hello\": \"world\",\n    \"key1\": {\n        \"$ref\": \"file2.yml#/list\"\n    }\n}\n",
+    )
+    .unwrap();
+    std::fs::write(&path_file_2, "list: hallo!\n").unwrap();
+    {
+        let mut sparse_root: SparseRoot<SimpleStruct1> =
+            SparseRoot::new_from_file(path_file_1.clone()).unwrap();
+        {
+            let state = sparse_root.state().try_borrow_state().unwrap();
+            assert_eq!(
+                state.get_state_file(&path_file_2).unwrap().ftype(),
+                SparseFileFormat::Yaml,
+                "The referenced file should be read as YAML"
+            );
+        }
+        let state = sparse_root.state().clone();
+        let mut hello: SparseValueMut<SimpleStruct1> = sparse_root.root_get_mut().unwrap();
+
+        let mut key = hello.key1.get_mut(state).unwrap();
+        *key = "unbelievable".to_string();
+        key.sparse_save().unwrap();
+        sparse_root.sparse_updt().unwrap();
+        sparse_root.save_to_disk(None).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path_file_2).unwrap(),
+            "list: unbelievable\n",
+            "The referenced file should be saved as YAML"
+        );
+
+        let mut hello: SparseValueMut<SimpleStruct1> = sparse_root.root_get_mut().unwrap();
+        // Changing the type of a value forces the document to be serialized again
+        hello.hello = "toto".to_string();
+        hello.key1 = SparseSelector::Obj(SparsePointedValue::Obj("hello_world".to_string()));
+        hello.sparse_save().unwrap();
+        sparse_root.sparse_updt().unwrap();
+        sparse_root.save_to_disk(None).unwrap();
+    }
+    assert_eq!(
+        std::fs::read_to_string(&path_file_1).unwrap(),
+        "{\n    \"hello\": \"toto\",\n    \"key1\": \"hello_world\"\n}\n",
+        "The indentation of the JSON document should be kept"
+    );
+}
@@ -0,0 +1,85 @@
+use super::*;
+
+#[test]
+fn from_str_yaml() {
+    let root: SparseRoot<SimpleStruct1> = SparseRoot::new_from_str(
+        "hello: world\nkey1:\n  $ref: \"#/hello\"\n",
+        PathBuf::from("upload.yaml"),
+        None,
+    )
+    .unwrap();
+
+    assert_eq!(
+        *root.root_get().unwrap().key1().get().unwrap(),
+        "world",
+        "The local reference should be resolved"
+    );
+}
+
+#[test]
+fn from_slice_detect() {
+    let root: SparseRoot<SimpleStruct1> = SparseRoot::new_from_slice(
+        b"{\"hello\": \"world\", \"key1\": {\"$ref\": \"#/hello\"}}",
+        PathBuf::from("upload"),
+        None,
+    )
+    .unwrap();
+    let state = root.state().try_borrow_state().unwrap();
+
+    assert_eq!(
+        state.get_state_file(state.get_root_path()).unwrap().ftype(),
+        SparseFileFormat::Json(false),
+        "The format should be detected from the content"
+    );
+}
+
+#[test]
+fn from_slice_format() {
+    let err = SparseRoot::<SimpleStruct1>::new_from_slice(
+        b"hello: world\nkey1:\n  $ref: \"#/hello\"\n",
+        PathBuf::from("upload.yaml"),
+        Some(SparseFileFormat::Json(true)),
+    )
+    .expect_err("The document isn't JSON");
+
+    match err {
+        SparseError::SerdeJson(_) => (),
+        _ => panic!("Expected the err to be `SerdeJson`"),
+    }
+}
+
+#[test]
+fn from_reader() {
+    let file = std::fs::File::open(sparse_test_rel_path!("./src/tests/docs/simple.json")).unwrap();
+    let root: SparseRoot<Value> =
+        SparseRoot::new_from_reader(file, PathBuf::from("simple.json"), None).unwrap();
+
+    assert_eq!(
+        root.root_get().unwrap().get("hello"),
+        Some(&Value::String("world".to_string())),
+        "The document should be read from the reader"
+    );
+}
+
+#[test]
+fn from_str_dirty() {
+    let temp_dir = tempfile::tempdir().unwrap(); // Setting up temp dir
+    let path = temp_dir.path().join("upload.yaml");
+    let root: SparseRoot<SimpleStruct1> = SparseRoot::new_from_str(
+        "hello: world\nkey1:\n  $ref: \"#/hello\"\n",
+        path.clone(),
+        None,
+    )
+    .unwrap();
+
+    assert_eq!(
+        root.save_to_disk(None).unwrap(),
+        vec![path.clone()],
+        "The document should be written at its virtual path"
+    );
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "hello: world\nkey1:\n  $ref: \"#/hello\"\n",
+        "The document should be written as is"
+    );
+}
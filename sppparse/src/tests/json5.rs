@@ -0,0 +1,77 @@
+use super::*;
+
+const JSON5_DOC: &str = "// The root document\n{\n  hello: 'world', // A comment\n  /* The reference */\n  \"key1\": {\"$ref\": \"#/hello\"},\n}\n";
+
+#[test]
+fn json5_root() {
+    let temp_dir = tempfile::tempdir().unwrap(); // Setting up temp dir
+    let path_file_1 = temp_dir.path().join("root.json");
+    std::fs::write(&path_file_1, JSON5_DOC).unwrap();
+
+    let sparse_root: SparseRoot<SimpleStruct1> =
+        SparseRoot::new_from_file(path_file_1.clone()).unwrap();
+    let root = sparse_root.root_get().unwrap();
+    assert_eq!(root.hello(), "world", "The JSON5 value doesn't match");
+    assert_eq!(
+        *root.key1().get().unwrap(),
+        "world",
+        "The reference mismatch"
+    );
+    let state = sparse_root.state().try_borrow_state().unwrap();
+    let state_file = state.get_state_file(&path_file_1).unwrap();
+    assert_eq!(
+        state_file.ftype(),
+        SparseFileFormat::Json5,
+        "The file should be read as JSON5"
+    );
+    let span = state_file
+        .span(&JsonPointer::parse("/key1/$ref").unwrap())
+        .expect("the span of the reference");
+    assert_eq!((span.line(), span.column()), (5, 20), "Span mismatch");
+}
+
+#[test]
+fn json5_save_keeps_comments() {
+    let temp_dir = tempfile::tempdir().unwrap(); // Setting up temp dir
+    let path_file_1 = temp_dir.path().join("root.json5");
+    std::fs::write(&path_file_1, JSON5_DOC).unwrap();
+    {
+        let mut sparse_root: SparseRoot<SimpleStruct1> =
+            SparseRoot::new_from_file(path_file_1.clone()).unwrap();
+        let mut hello: SparseValueMut<SimpleStruct1> = sparse_root.root_get_mut().unwrap();
+
+        hello.hello = "universe".to_string();
+        hello.sparse_save().unwrap();
+        sparse_root.sparse_updt().unwrap();
+        sparse_root.save_to_disk(None).unwrap();
+    }
+    assert_eq!(
+        std::fs::read_to_string(&path_file_1).unwrap(),
+        JSON5_DOC.replace("'world'", "'universe'"),
+        "Only the modified value should be rewritten"
+    );
+}
+
+#[test]
+fn json5_save_as_json() {
+    let temp_dir = tempfile::tempdir().unwrap(); // Setting up temp dir
+    let path_file_1 = temp_dir.path().join("root.jsonc");
+    std::fs::write(&path_file_1, JSON5_DOC).unwrap();
+    {
+        let mut sparse_root: SparseRoot<SimpleStruct1> =
+            SparseRoot::new_from_file(path_file_1.clone()).unwrap();
+        let mut hello: SparseValueMut<SimpleStruct1> = sparse_root.root_get_mut().unwrap();
+
+        hello.hello = "universe".to_string();
+        hello.sparse_save().unwrap();
+        sparse_root.sparse_updt().unwrap();
+        sparse_root
+            .save_to_disk(Some(SparseFileFormat::Json(false)))
+            .unwrap();
+    }
+    assert_eq!(
+        std::fs::read_to_string(&path_file_1).unwrap(),
+        "{\"hello\":\"universe\",\"key1\":{\"$ref\":\"#/hello\"}}",
+        "The file should be saved as plain JSON"
+    );
+}
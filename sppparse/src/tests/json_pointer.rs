@@ -0,0 +1,167 @@
+use super::*;
+use serde_json::json;
+use std::str::FromStr;
+
+#[test]
+fn parse_escaped() {
+    let ptr = JsonPointer::parse("/paths/~1pets~1{id}/get/a~0b").unwrap();
+
+    assert_eq!(
+        ptr.tokens(),
+        &["paths", "/pets/{id}", "get", "a~b"],
+        "The tokens weren't unescaped"
+    );
+    assert_eq!(
+        ptr.to_string(),
+        "/paths/~1pets~1{id}/get/a~0b",
+        "The pointer doesn't round-trip"
+    );
+}
+
+#[test]
+fn parse_root() {
+    let ptr = JsonPointer::parse("").unwrap();
+
+    assert!(ptr.is_root(), "The empty pointer should be the root");
+    assert_eq!(ptr.to_string(), "");
+    assert_eq!(
+        JsonPointer::parse("/").unwrap().tokens(),
+        &[""],
+        "`/` should reference the empty key"
+    );
+}
+
+#[test]
+fn parse_invalid() {
+    for invalid in ["/hello~", "/hello~2", "hello"].iter() {
+        match JsonPointer::parse(invalid).expect_err("The pointer is invalid") {
+            SparseError::InvalidPointer(_) => (),
+            _ => panic!("Expected the err to be `InvalidPointer`"),
+        }
+    }
+}
+
+#[test]
+fn push_pop() {
+    let mut ptr = JsonPointer::new();
+
+    ptr.push("paths");
+    ptr.push("/pets");
+    assert_eq!(ptr.to_string(), "/paths/~1pets");
+    assert_eq!(ptr.pop(), Some("/pets".to_string()));
+    assert_eq!(ptr, JsonPointer::from_str("/paths").unwrap());
+}
+
+#[test]
+fn get() {
+    let val: Value = json!({
+        "list": ["zero", "one"],
+        "a/b": {
+            "c~d": "hello"
+        }
+    });
+
+    assert_eq!(
+        JsonPointer::parse("/a~1b/c~0d").unwrap().get(&val),
+        Some(&json!("hello"))
+    );
+    assert_eq!(
+        JsonPointer::parse("/list/1").unwrap().get(&val),
+        Some(&json!("one"))
+    );
+    assert_eq!(JsonPointer::parse("/list/01").unwrap().get(&val), None);
+    assert_eq!(JsonPointer::parse("/list/-").unwrap().get(&val), None);
+    assert_eq!(JsonPointer::new().get(&val), Some(&val));
+}
+
+#[test]
+fn metadata_invalid() {
+    let err = SparseMetadata::new(
+        "#/hello~3".to_string(),
+        PathBuf::from_str("hello.json").unwrap(),
+    )
+    .expect_err("The pointer is invalid");
+
+    match err {
+        SparseError::InvalidPointer(_) => (),
+        _ => panic!("Expected the err to be `InvalidPointer`"),
+    }
+}
+
+#[test]
+fn round_trip() {
+    let val: Value = json!({
+        "hello": "world",
+        "paths": {
+            "/pets/{id}": {
+                "get": "pet"
+            }
+        },
+        "key1": {
+            "$ref": "#/paths/~1pets~1{id}/get"
+        }
+    });
+    let mut parsed: SparseRoot<SimpleStruct1> =
+        SparseRoot::new_from_value(val, PathBuf::from_str("hello.json").unwrap(), vec![]).unwrap();
+    let state = parsed.state().clone();
+
+    assert_eq!(
+        *parsed.root_get().unwrap().key1.get().unwrap(),
+        "pet",
+        "The dereferenced value doesn't match"
+    );
+    {
+        let mut val_parsed: SparseValueMut<'_, SimpleStruct1> = parsed.root_get_mut().unwrap();
+        let mut key: SparseValueMut<'_, String> = val_parsed.key1.get_mut(state.clone()).unwrap();
+        *key = String::from("cat");
+        key.sparse_save().unwrap();
+    }
+    parsed.sparse_updt().unwrap();
+    assert_eq!(
+        *parsed.root_get().unwrap().key1.get().unwrap(),
+        "cat",
+        "The dereferenced value doesn't match"
+    );
+    let state = state.try_borrow_state().unwrap();
+    let root = state.get_state_file(state.get_root_path()).unwrap().val();
+    assert_eq!(root["paths"]["/pets/{id}"]["get"], json!("cat"));
+}
+
+#[test]
+fn uri_fragment() {
+    let ptr = JsonPointer::parse_uri_fragment("/components/schemas/My%20Type/%7Bid%7D").unwrap();
+
+    assert_eq!(
+        ptr.tokens(),
+        &["components", "schemas", "My Type", "{id}"],
+        "The fragment wasn't decoded"
+    );
+    assert_eq!(
+        ptr.to_uri_fragment(),
+        "#/components/schemas/My%20Type/%7Bid%7D",
+        "The fragment doesn't round-trip"
+    );
+    match JsonPointer::parse_uri_fragment("/hello%FF").expect_err("The fragment is invalid") {
+        SparseError::InvalidPointer(_) => (),
+        _ => panic!("Expected the err to be `InvalidPointer`"),
+    }
+}
+
+#[test]
+fn metadata_uri_fragment() {
+    let r = SparseMetadata::new(
+        "./world.json#/paths/~1pets~1%7Bid%7D/My%20Type".to_string(),
+        PathBuf::from_str("hello.json").unwrap(),
+    )
+    .unwrap();
+
+    assert_eq!(r.pointer().tokens(), &["paths", "/pets/{id}", "My Type"]);
+    assert_eq!(
+        SparseMetadata::new("/My%20Type".to_string(), PathBuf::new())
+            .unwrap()
+            .pointer()
+            .tokens(),
+        &["My%20Type"],
+        "A plain JSON pointer shouldn't be decoded"
+    );
+}
@@ -0,0 +1,212 @@
+use super::*;
+use serde_json::json;
+use std::str::FromStr;
+
+#[derive(Serialize, Deserialize, SparsableInner, Debug, Getters)]
+struct Node {
+    #[getset(get = "pub")]
+    name: String,
+    #[getset(get = "pub")]
+    children: Vec<SparseRefLazy<Node>>,
+}
+
+#[derive(Serialize, Deserialize, SparsableInner, Debug, Getters)]
+struct Tree {
+    #[getset(get = "pub")]
+    node: Node,
+    #[getset(get = "pub")]
+    definitions: HashMap<String, Node>,
+}
+
+fn recursive_val() -> Value {
+    json!({
+        "node": {
+            "name": "a",
+            "children": [
+                { "$ref": "#/definitions/b" }
+            ]
+        },
+        "definitions": {
+            "b": {
+                "name": "b",
+                "children": [
+                    { "$ref": "#/definitions/b" },
+                    { "$ref": "#/node" }
+                ]
+            }
+        }
+    })
+}
+
+#[test]
+fn recursive() {
+    let parsed: SparseRoot<Tree> = SparseRoot::new_from_value(
+        recursive_val(),
+        PathBuf::from_str("hello.json").unwrap(),
+        vec![],
+    )
+    .unwrap();
+    let state = parsed.state().clone();
+    let root = parsed.root_get().unwrap();
+    let child = &root.node.children[0];
+
+    assert!(
+        !child.is_resolved(),
+        "The reference shouldn't be resolved yet"
+    );
+    let b = child.get(state.clone()).unwrap();
+    assert!(child.is_resolved(), "The reference should be resolved");
+    assert_eq!(b.name, "b", "The dereferenced value doesn't match");
+    let b_again = b.children[0].get(state.clone()).unwrap();
+    assert_eq!(b_again.name, "b", "The dereferenced value doesn't match");
+    assert!(
+        std::ptr::eq(&*b, &*b_again),
+        "The resolved value should be shared"
+    );
+    assert_eq!(
+        b_again.children[1].get(state).unwrap().name,
+        "a",
+        "The dereferenced value doesn't match"
+    );
+}
+
+#[test]
+fn updated() {
+    let mut parsed: SparseRoot<Tree> = SparseRoot::new_from_value(
+        recursive_val(),
+        PathBuf::from_str("hello.json").unwrap(),
+        vec![],
+    )
+    .unwrap();
+    let state = parsed.state().clone();
+
+    assert_eq!(
+        parsed.root_get().unwrap().node.children[0]
+            .get(state.clone())
+            .unwrap()
+            .name,
+        "b"
+    );
+    {
+        let mut val_parsed: SparseValueMut<'_, Tree> = parsed.root_get_mut().unwrap();
+        val_parsed.node.name = String::from("c");
+        val_parsed.sparse_save().unwrap();
+    }
+    parsed.sparse_updt().unwrap();
+    let root = parsed.root_get().unwrap();
+    let b = root.node.children[0].get(state.clone()).unwrap();
+
+    assert_eq!(
+        b.children[1].get(state).unwrap().name,
+        "c",
+        "The dereferenced value should've been updated"
+    );
+}
+
+#[test]
+fn serialize() {
+    let lazy: SparseRefLazy<Node> =
+        SparseRefLazy::new(PathBuf::from("hello.json"), String::from("#/node")).unwrap();
+
+    assert_eq!(
+        serde_json::to_value(&lazy).unwrap(),
+        json!({ "$ref": "#/node" }),
+        "The reference should serialize to its raw pointer"
+    );
+}
+
+#[test]
+fn refs_limit() {
+    let val = json!({
+        "node": {
+            "name": "a",
+            "children": [{ "$ref": "#/definitions/b" }]
+        },
+        "definitions": {
+            "b": { "name": "b", "children": [{ "$ref": "#/definitions/c" }] },
+            "c": { "name": "c", "children": [{ "$ref": "#/definitions/d" }] },
+            "d": { "name": "d", "children": [] }
+        }
+    });
+    let parsed: SparseRoot<Tree> = SparseRoot::builder()
+        .limits(SparseLimits::new().limit_refs(1))
+        .build_from_value(PathBuf::from_str("hello.json").unwrap(), val)
+        .unwrap();
+    let state = parsed.state().clone();
+    let root = parsed.root_get().unwrap();
+
+    let b = root.node.children[0].get(state.clone()).unwrap();
+    let c = b.children[0].get(state.clone()).unwrap();
+    let d = c.children[0].get(state).unwrap();
+    assert_eq!(
+        d.name, "d",
+        "The references should be counted for each lazy resolution"
+    );
+}
+
+static COUNTED_ALIVE: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+
+/// Count the values alive
+#[derive(Debug)]
+struct DropCounter;
+
+impl Default for DropCounter {
+    fn default() -> Self {
+        COUNTED_ALIVE.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        DropCounter
+    }
+}
+
+impl Drop for DropCounter {
+    fn drop(&mut self) {
+        COUNTED_ALIVE.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+    }
+}
+
+impl SparsableTrait for DropCounter {
+    fn sparse_init(
+        &mut self,
+        _state: &mut SparseState,
+        _metadata: &SparseMetadata,
+        _depth: u32,
+    ) -> Result<(), SparseError> {
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize, SparsableInner, Debug)]
+struct CountedNode {
+    #[serde(skip)]
+    counter: DropCounter,
+    next: SparseRefLazy<CountedNode>,
+}
+
+#[derive(Serialize, Deserialize, SparsableInner, Debug)]
+struct CountedTree {
+    node: CountedNode,
+}
+
+#[test]
+fn recursive_drop() {
+    {
+        let parsed: SparseRoot<CountedTree> = SparseRoot::new_from_value(
+            json!({ "node": { "next": { "$ref": "#/node" } } }),
+            PathBuf::from_str("hello.json").unwrap(),
+            vec![],
+        )
+        .unwrap();
+        let state = parsed.state().clone();
+        let root = parsed.root_get().unwrap();
+        let next = root.node.next.get(state.clone()).unwrap();
+        let next_again = next.next.get(state).unwrap();
+        assert!(
+            std::ptr::eq(&*next, &*next_again),
+            "The resolved value should be shared"
+        );
+    }
+    assert_eq!(
+        COUNTED_ALIVE.load(std::sync::atomic::Ordering::SeqCst),
+        0,
+        "The recursive value shouldn't own itself"
+    );
+}
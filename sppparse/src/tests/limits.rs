@@ -0,0 +1,141 @@
+use super::*;
+
+/// The lists of [LAUGHS_DOC], each item referencing the next list
+type LaughsC = Vec<SparseSelector<String>>;
+type LaughsB = Vec<SparseSelector<LaughsC>>;
+
+#[derive(Serialize, Deserialize, SparsableInner, Debug)]
+struct Laughs {
+    a: Vec<SparseSelector<LaughsB>>,
+}
+
+/// A document whose references expand 4^3 times when dereferenced
+const LAUGHS_DOC: &str = "a: [{$ref: \"#/b\"}, {$ref: \"#/b\"}, {$ref: \"#/b\"}, {$ref: \"#/b\"}]\nb: [{$ref: \"#/c\"}, {$ref: \"#/c\"}, {$ref: \"#/c\"}, {$ref: \"#/c\"}]\nc: [{$ref: \"#/d\"}, {$ref: \"#/d\"}, {$ref: \"#/d\"}, {$ref: \"#/d\"}]\nd: lol\n";
+
+fn check_limit(err: SparseError, expected: SparseLimit) {
+    let err = match err {
+        SparseError::BrokenRef { source, .. } => *source,
+        err => err,
+    };
+    match err {
+        SparseError::LimitExceeded { limit, .. } if limit == expected => (),
+        _ => panic!("Expected the err to be `LimitExceeded` for {}", expected),
+    }
+}
+
+fn distant_state(limits: SparseLimits) -> Result<SimpleStruct1, SparseError> {
+    let temp_dir = tempfile::tempdir().unwrap(); // Setting up temp dir
+    let path_file_1 = temp_dir.path().join("root.yaml");
+    std::fs::write(
+        &path_file_1,
+        "hello: world\nkey1:\n  $ref: \"other.yaml#/greeting\"\n",
+    )
+    .unwrap();
+    std::fs::write(temp_dir.path().join("other.yaml"), "greeting: universe\n").unwrap();
+    let mut state = SparseState::new_from_file(path_file_1).unwrap();
+    state.set_limits(limits);
+    state.parse_root()
+}
+
+#[test]
+fn file_size() {
+    check_limit(
+        distant_state(SparseLimits::new().limit_file_size(8))
+            .expect_err("The distant file is too big"),
+        SparseLimit::FileSize,
+    );
+    distant_state(SparseLimits::new().limit_file_size(1024)).unwrap();
+}
+
+#[test]
+fn files() {
+    check_limit(
+        distant_state(SparseLimits::new().limit_files(1)).expect_err("There are too many files"),
+        SparseLimit::Files,
+    );
+    distant_state(SparseLimits::new().limit_files(2)).unwrap();
+}
+
+fn laughs_state(limits: SparseLimits) -> Result<Value, SparseError> {
+    let temp_dir = tempfile::tempdir().unwrap(); // Setting up temp dir
+    let path_file_1 = temp_dir.path().join("root.yaml");
+    std::fs::write(&path_file_1, LAUGHS_DOC).unwrap();
+    let mut state = SparseState::new_from_file(path_file_1).unwrap();
+    state.set_limits(limits);
+    state.to_dereferenced_value(SparseCycleBehavior::Error)
+}
+
+#[test]
+fn refs() {
+    check_limit(
+        laughs_state(SparseLimits::new().limit_refs(50))
+            .expect_err("There are too many references"),
+        SparseLimit::Refs,
+    );
+    laughs_state(SparseLimits::new().limit_refs(200)).unwrap();
+}
+
+#[test]
+fn expansion() {
+    check_limit(
+        laughs_state(SparseLimits::new().limit_expansion(2))
+            .expect_err("The document expands too much"),
+        SparseLimit::Expansion,
+    );
+    laughs_state(SparseLimits::new()).unwrap();
+}
+
+fn laughs_root(limits: SparseLimits) -> Result<SparseRoot<Laughs>, SparseError> {
+    let temp_dir = tempfile::tempdir().unwrap(); // Setting up temp dir
+    let path_file_1 = temp_dir.path().join("root.yaml");
+    std::fs::write(&path_file_1, LAUGHS_DOC).unwrap();
+    SparseRoot::builder()
+        .limits(limits)
+        .build_from_file(path_file_1)
+}
+
+#[test]
+fn expansion_init() {
+    check_limit(
+        laughs_root(SparseLimits::new().limit_expansion(2))
+            .expect_err("The document expands too much when initialized"),
+        SparseLimit::Expansion,
+    );
+    laughs_root(SparseLimits::new().limit_expansion(100)).unwrap();
+}
+
+#[test]
+fn file_size_before_read() {
+    let temp_dir = tempfile::tempdir().unwrap(); // Setting up temp dir
+    let path = temp_dir.path().join("big.yaml");
+    std::fs::write(&path, "greeting: universe\n").unwrap();
+    let limits = SparseLimits::new().limit_file_size(8);
+
+    check_limit(
+        SparseFileLoader
+            .load_limited(&path, &limits)
+            .expect_err("The file is too big"),
+        SparseLimit::FileSize,
+    );
+    check_limit(
+        SparseMemoryFs::new()
+            .with_file(PathBuf::from("big.yaml"), "greeting: universe\n")
+            .load_limited(std::path::Path::new("/big.yaml"), &limits)
+            .expect_err("The file is too big"),
+        SparseLimit::FileSize,
+    );
+    check_limit(
+        limits
+            .read_to_end("greeting: universe\n".as_bytes())
+            .expect_err("The reader is too big"),
+        SparseLimit::FileSize,
+    );
+    assert_eq!(
+        SparseFileLoader
+            .load_limited(&path, &SparseLimits::new().limit_file_size(1024))
+            .unwrap()
+            .0,
+        b"greeting: universe\n",
+        "The file should be read whole when within the limit"
+    );
+}
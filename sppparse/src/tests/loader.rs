@@ -0,0 +1,83 @@
+use super::*;
+use std::path::Path;
+
+#[derive(Debug, Default)]
+struct MapLoader {
+    files: HashMap<PathBuf, String>,
+}
+
+impl SparseLoader for MapLoader {
+    fn load(&self, path: &Path) -> Result<(Vec<u8>, SparseFileFormat), SparseError> {
+        let raw = self
+            .files
+            .get(path)
+            .ok_or(SparseError::NotInState)?
+            .as_bytes()
+            .to_vec();
+        let format = SparseFileFormat::detect(path, &raw)?;
+        Ok((raw, format))
+    }
+}
+
+fn map_loader() -> SparseRc<dyn SparseLoader> {
+    let mut loader = MapLoader::default();
+    loader.files.insert(
+        PathBuf::from("/virtual/root.json"),
+        r##"{"hello": "world", "key1": {"$ref": "other.yaml#/key1"}}"##.to_string(),
+    );
+    loader.files.insert(
+        PathBuf::from("/virtual/other.yaml"),
+        "key1:\n  $ref: \"root.json#/hello\"\n".to_string(),
+    );
+    SparseRc::new(loader)
+}
+
+#[test]
+fn custom_loader() {
+    let root: SparseRoot<SimpleStruct1> =
+        SparseRoot::new_from_file_with_loader(PathBuf::from("/virtual/root.json"), map_loader())
+            .unwrap();
+
+    assert_eq!(
+        *root.root_get().unwrap().key1.get().unwrap(),
+        "world",
+        "The dereferenced value doesn't match"
+    );
+}
+
+#[test]
+fn custom_loader_format() {
+    let root: SparseRoot<SimpleStruct1> =
+        SparseRoot::new_from_file_with_loader(PathBuf::from("/virtual/root.json"), map_loader())
+            .unwrap();
+    let state = root.state().try_borrow_state().unwrap();
+
+    assert!(matches!(
+        state
+            .get_state_file(Path::new("/virtual/root.json"))
+            .unwrap()
+            .ftype(),
+        SparseFileFormat::Json(_)
+    ));
+    assert!(matches!(
+        state
+            .get_state_file(Path::new("/virtual/other.yaml"))
+            .unwrap()
+            .ftype(),
+        SparseFileFormat::Yaml
+    ));
+}
+
+#[test]
+fn custom_loader_missing() {
+    let err = SparseRoot::<SimpleStruct1>::new_from_file_with_loader(
+        PathBuf::from("/virtual/nothing.json"),
+        map_loader(),
+    )
+    .expect_err("The loader doesn't know that file");
+
+    match err {
+        SparseError::NotInState => (),
+        _ => panic!("Expected the err to be `NotInState`"),
+    };
+}
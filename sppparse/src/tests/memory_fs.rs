@@ -0,0 +1,111 @@
+use super::*;
+use std::path::Path;
+
+fn memory_fs() -> SparseMemoryFs {
+    SparseMemoryFs::new()
+        .with_file(
+            PathBuf::from("specs/api/root.yaml"),
+            "hello: world\nkey1:\n  $ref: \"../common/greetings.json#/greeting\"\n",
+        )
+        .with_file(
+            PathBuf::from("/specs/common/greetings.json"),
+            r#"{"greeting": {"$ref": "./raw#/value"}}"#,
+        )
+        .with_file_format(
+            PathBuf::from("/specs/common/raw"),
+            "value: universe\n",
+            SparseFileFormat::Yaml,
+        )
+}
+
+#[test]
+fn relative_refs() {
+    let root: SparseRoot<SimpleStruct1> = SparseRoot::builder()
+        .loader(SparseRc::new(memory_fs()))
+        .base_path(PathBuf::from("/specs"))
+        .build_from_file(PathBuf::from("api/root.yaml"))
+        .unwrap();
+
+    assert_eq!(
+        *root.root_get().unwrap().key1().get().unwrap(),
+        "universe",
+        "The relative references should be resolved in the memory file system"
+    );
+    let state = root.state().try_borrow_state().unwrap();
+    assert_eq!(
+        state
+            .get_state_file(Path::new("/specs/common/raw"))
+            .unwrap()
+            .ftype(),
+        SparseFileFormat::Yaml,
+        "The format of the file should be the one it was inserted with"
+    );
+}
+
+#[test]
+fn from_str() {
+    let root: SparseRoot<SimpleStruct1> = SparseRoot::builder()
+        .loader(SparseRc::new(memory_fs()))
+        .build_from_str(
+            PathBuf::from("/specs/api/upload.yaml"),
+            "hello: world\nkey1:\n  $ref: \"../common/raw#/value\"\n",
+            None,
+        )
+        .unwrap();
+
+    assert_eq!(
+        *root.root_get().unwrap().key1().get().unwrap(),
+        "universe",
+        "A document from memory should reference the memory file system"
+    );
+}
+
+#[test]
+fn not_found() {
+    let err = SparseRoot::<SimpleStruct1>::builder()
+        .loader(SparseRc::new(
+            memory_fs().with_file(PathBuf::from("/specs/common/greetings.json"), "{}"),
+        ))
+        .build_from_file(PathBuf::from("/specs/api/root.yaml"))
+        .expect_err("The pointer is dangling");
+
+    match err {
+        SparseError::BrokenRef { source, .. } => match *source {
+            SparseError::UnkownPath(_) => (),
+            _ => panic!("Expected the source to be `UnkownPath`"),
+        },
+        _ => panic!("Expected the err to be `BrokenRef`"),
+    }
+
+    let mut fs = memory_fs();
+    assert!(
+        fs.remove(Path::new("/specs/common/raw")).is_some(),
+        "The file should exist"
+    );
+    let err = SparseRoot::<SimpleStruct1>::builder()
+        .loader(SparseRc::new(fs))
+        .build_from_file(PathBuf::from("/specs/api/root.yaml"))
+        .expect_err("The file doesn't exist");
+    match err {
+        SparseError::BrokenRef { source, .. } => match *source {
+            SparseError::Io(_) => (),
+            _ => panic!("Expected the source to be `Io`"),
+        },
+        _ => panic!("Expected the err to be `BrokenRef`"),
+    }
+}
+
+#[test]
+fn files_in() {
+    let fs = memory_fs();
+
+    assert_eq!(
+        fs.files_in(Path::new("/specs/common")),
+        vec![
+            PathBuf::from("/specs/common/greetings.json"),
+            PathBuf::from("/specs/common/raw")
+        ],
+        "The files of the directory mismatch"
+    );
+    assert!(fs.contains(Path::new("specs/api/../api/root.yaml")));
+}
@@ -1,14 +1,36 @@
 use super::*;
 use sppparse_derive::SparsableInner;
+mod builder;
+mod bundle;
+mod dereference;
+mod format;
+mod from_raw;
 mod inline;
+#[cfg(feature = "json5")]
+mod json5;
+mod json_pointer;
+mod lazy;
+mod limits;
+mod loader;
+mod memory_fs;
 mod pfile_path;
 mod pointer_parsing;
+mod policy;
 mod recursive;
 mod ref_get_distant;
 mod ref_get_local;
 mod save_file;
 mod simple_obj;
+mod spans;
+#[cfg(feature = "sync")]
+mod sync;
+#[cfg(feature = "toml")]
+mod toml;
 mod updating;
+#[cfg(feature = "url")]
+mod url;
+mod yaml_merge;
+mod yaml_stream;
 
 #[macro_export]
 macro_rules! sparse_test_rel_path {
@@ -71,7 +71,7 @@ fn get_pfile_path_distant_local_ref() {
 #[test]
 fn get_pfile_path_distant_distant_ref_relative() {
     let mut expected =
-        std::fs::canonicalize(&PathBuf::from(sparse_test_rel_path!("./examples"))).unwrap();
+        std::fs::canonicalize(PathBuf::from(sparse_test_rel_path!("./examples"))).unwrap();
     expected.push("read_single_file.json");
     let mut state: SparseState = SparseState::new_from_file(
         PathBuf::from_str(sparse_test_rel_path!("./examples/selector.json")).unwrap(),
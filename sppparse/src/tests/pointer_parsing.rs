@@ -4,7 +4,8 @@ fn ref_pointer_local_helper(raw_pointer: &str, expected_pointer: &str) {
     let r = SparseMetadata::new(
         raw_pointer.to_string(),
         PathBuf::from(sparse_test_rel_path!("./examples/selector.json")),
-    );
+    )
+    .unwrap();
 
     assert_eq!(
         r.pfile_path(),
@@ -18,7 +19,8 @@ fn ref_pointer_distant_helper(raw_pointer: &str, expected_path: &str, expected_p
     let r = SparseMetadata::new(
         raw_pointer.to_string(),
         PathBuf::from(sparse_test_rel_path!("./examples/selector.json")),
-    );
+    )
+    .unwrap();
     let root = sparse_test_rel_path!("./examples/");
     let mut distant_path = PathBuf::from(root);
     distant_path.push(PathBuf::from(expected_path));
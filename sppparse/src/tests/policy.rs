@@ -0,0 +1,177 @@
+use super::*;
+
+/// Set up a sandbox with a root document referencing `reference`, and a secret outside of it
+fn sandbox(reference: &str) -> (tempfile::TempDir, PathBuf, PathBuf) {
+    let temp_dir = tempfile::tempdir().unwrap(); // Setting up temp dir
+    let sandbox_dir = temp_dir.path().join("sandbox");
+    std::fs::create_dir(&sandbox_dir).unwrap();
+    std::fs::write(temp_dir.path().join("secret.yaml"), "hello: secret\n").unwrap();
+    std::fs::write(sandbox_dir.join("inside.yaml"), "hello: inside\n").unwrap();
+    let root_path = sandbox_dir.join("root.yaml");
+    std::fs::write(
+        &root_path,
+        format!("hello: world\nkey1:\n  $ref: \"{}\"\n", reference),
+    )
+    .unwrap();
+    (temp_dir, sandbox_dir, root_path)
+}
+
+fn parse_sandboxed(root_path: PathBuf, policy: SparsePolicy) -> Result<String, SparseError> {
+    let mut state = SparseState::new_from_file(root_path).unwrap();
+    state.set_policy(policy);
+    let root: SimpleStruct1 = state.parse_root()?;
+    let res = root.key1().get()?.to_string();
+    Ok(res)
+}
+
+fn check_forbidden(err: SparseError) {
+    match err {
+        SparseError::BrokenRef { source, .. } => match *source {
+            SparseError::Forbidden { .. } => (),
+            _ => panic!("Expected the source to be `Forbidden`"),
+        },
+        _ => panic!("Expected the err to be `BrokenRef`"),
+    }
+}
+
+#[test]
+fn inside_root() {
+    let (_temp_dir, sandbox_dir, root_path) = sandbox("./inside.yaml#/hello");
+
+    assert_eq!(
+        parse_sandboxed(root_path, SparsePolicy::sandboxed(sandbox_dir)).unwrap(),
+        "inside",
+        "The files inside the root should be allowed"
+    );
+}
+
+#[test]
+fn parent_escape() {
+    let (_temp_dir, sandbox_dir, root_path) = sandbox("../secret.yaml#/hello");
+
+    check_forbidden(
+        parse_sandboxed(root_path.clone(), SparsePolicy::sandboxed(sandbox_dir))
+            .expect_err("The file is outside of the root"),
+    );
+    assert_eq!(
+        parse_sandboxed(root_path, SparsePolicy::default()).unwrap(),
+        "secret",
+        "The default policy shouldn't restrict anything"
+    );
+}
+
+#[test]
+fn absolute_path() {
+    let (_temp_dir, sandbox_dir, root_path) = sandbox("");
+    std::fs::write(
+        &root_path,
+        format!(
+            "hello: world\nkey1:\n  $ref: \"{}#/hello\"\n",
+            sandbox_dir.join("inside.yaml").to_string_lossy()
+        ),
+    )
+    .unwrap();
+
+    check_forbidden(
+        parse_sandboxed(
+            root_path.clone(),
+            SparsePolicy::new().deny_absolute_paths(true),
+        )
+        .expect_err("The absolute paths are denied"),
+    );
+    assert_eq!(
+        parse_sandboxed(root_path, SparsePolicy::new()).unwrap(),
+        "inside",
+        "The absolute paths should be allowed by default"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn symlink_escape() {
+    let (temp_dir, sandbox_dir, root_path) = sandbox("./link.yaml#/hello");
+    std::os::unix::fs::symlink(
+        temp_dir.path().join("secret.yaml"),
+        sandbox_dir.join("link.yaml"),
+    )
+    .unwrap();
+
+    check_forbidden(
+        parse_sandboxed(
+            root_path.clone(),
+            SparsePolicy::sandboxed(sandbox_dir.clone()),
+        )
+        .expect_err("The symlink escapes the root"),
+    );
+    assert_eq!(
+        parse_sandboxed(root_path, SparsePolicy::new().allow_root(sandbox_dir)).unwrap(),
+        "secret",
+        "The symlinks should be followed unless denied"
+    );
+}
+
+#[cfg(feature = "url")]
+#[test]
+fn url_scheme() {
+    let (_temp_dir, sandbox_dir, root_path) = sandbox("https://example.com/secret.yaml#/hello");
+
+    check_forbidden(
+        parse_sandboxed(root_path, SparsePolicy::sandboxed(sandbox_dir))
+            .expect_err("The URLs are denied"),
+    );
+}
+
+#[test]
+fn dotted_path() {
+    let (_temp_dir, sandbox_dir, root_path) = sandbox("./missing/../inside.yaml#/hello");
+
+    let mut state = SparseState::new_from_file(root_path).unwrap();
+    state.set_policy(SparsePolicy::sandboxed(sandbox_dir.clone()));
+    let root: SimpleStruct1 = state.parse_root().unwrap();
+    assert_eq!(
+        root.key1().get().unwrap().to_string(),
+        "inside",
+        "The `..` should be resolved without the directory existing"
+    );
+    assert!(
+        state
+            .get_state_file(sandbox_dir.join("inside.yaml").as_path())
+            .is_ok(),
+        "The file should be stored under its clean path"
+    );
+}
+
+#[test]
+fn dotted_escape() {
+    let (_temp_dir, sandbox_dir, root_path) = sandbox("./inside/../../secret.yaml#/hello");
+
+    check_forbidden(
+        parse_sandboxed(root_path, SparsePolicy::sandboxed(sandbox_dir))
+            .expect_err("The file is outside of the root once cleaned"),
+    );
+}
+
+#[cfg(feature = "url")]
+#[test]
+fn file_url() {
+    let (_temp_dir, sandbox_dir, root_path) = sandbox("");
+    let url = url_inner::Url::from_file_path(sandbox_dir.join("inside.yaml")).unwrap();
+    std::fs::write(
+        &root_path,
+        format!("hello: world\nkey1:\n  $ref: \"{}#/hello\"\n", url),
+    )
+    .unwrap();
+
+    check_forbidden(
+        parse_sandboxed(
+            root_path.clone(),
+            SparsePolicy::new().deny_absolute_paths(true),
+        )
+        .expect_err("The file URLs are absolute paths"),
+    );
+    assert_eq!(
+        parse_sandboxed(root_path, SparsePolicy::new()).unwrap(),
+        "inside",
+        "The file URLs should be allowed by default"
+    );
+}
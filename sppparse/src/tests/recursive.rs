@@ -15,7 +15,13 @@ fn simple() {
             .expect_err("it's cyclic");
 
     match err {
-        SparseError::CyclicRef => (),
+        SparseError::CyclicRef(chain) => {
+            assert_eq!(chain.len(), 2, "The chain should be the reference twice");
+            assert!(
+                chain.iter().all(|x| x.ends_with("hello.json#/key1")),
+                "The chain should describe the reference"
+            );
+        }
         _ => panic!("The error should've been `CyclicRef`"),
     }
 }
@@ -44,7 +50,61 @@ fn double() {
     .expect_err("it's cyclic");
 
     match err {
-        SparseError::CyclicRef => (),
+        SparseError::CyclicRef(_) => (),
         _ => panic!("The error should've been `CyclicRef`"),
     }
 }
+
+#[test]
+fn cyclic_before_max_depth() {
+    let val: Value = json!({
+        "hello": "world",
+        "key1":
+        {
+            "$ref": "#/key2"
+        },
+        "key2":
+        {
+            "$ref": "#/key1"
+        }
+    });
+    let mut state = SparseState::new_from_value(PathBuf::from("hello.json"), val).unwrap();
+    state.set_max_depth(u32::MAX);
+    let err: SparseError = state
+        .parse_root::<SimpleStruct1>()
+        .expect_err("it's cyclic");
+
+    match err {
+        SparseError::CyclicRef(chain) => assert_eq!(
+            chain.len(),
+            3,
+            "The chain should go through both references"
+        ),
+        _ => panic!("The error should've been `CyclicRef`"),
+    }
+}
+
+#[test]
+fn max_depth() {
+    let val: Value = json!({
+        "hello": "world",
+        "key1":
+        {
+            "$ref": "#/key2"
+        },
+        "key2":
+        {
+            "$ref": "#/hello"
+        }
+    });
+    let mut state = SparseState::new_from_value(PathBuf::from("hello.json"), val).unwrap();
+    state.set_max_depth(3);
+    let err: SparseError = state
+        .parse_root::<SimpleStruct1>()
+        .expect_err("it's too deep");
+
+    match err {
+        SparseError::MaxDepthReached(3) => (),
+        _ => panic!("The error should've been `MaxDepthReached`"),
+    }
+}
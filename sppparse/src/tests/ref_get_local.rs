@@ -58,8 +58,11 @@ fn distant() {
         .expect_err("Supposed to fail, no distant file in a local state");
 
     match err {
-        SparseError::NoDistantFile => (),
-        _ => panic!("Expected the err to be `NoDistantFile`"),
+        SparseError::BrokenRef { source, .. } => match *source {
+            SparseError::NoDistantFile => (),
+            _ => panic!("Expected the err to be `NoDistantFile`"),
+        },
+        _ => panic!("Expected the err to be `BrokenRef`"),
     };
 }
 
@@ -79,7 +82,17 @@ fn not_found() {
         .expect_err("Supposed to fail, dangling pointer");
 
     match err {
-        SparseError::UnkownPath(path) => assert_eq!(path.as_str(), "/list/3"),
-        _ => panic!("Expected the err to be `UnkownPath`"),
+        SparseError::BrokenRef { location, source } => {
+            assert_eq!(
+                location.pointer(),
+                "/key1/$ref",
+                "The location should be the reference's"
+            );
+            match *source {
+                SparseError::UnkownPath(path) => assert_eq!(path.as_str(), "/list/3"),
+                _ => panic!("Expected the err to be `UnkownPath`"),
+            }
+        }
+        _ => panic!("Expected the err to be `BrokenRef`"),
     };
 }
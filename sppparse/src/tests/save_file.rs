@@ -1,4 +1,5 @@
 use super::*;
+use indexmap::IndexMap;
 use serde_json::json;
 use std::fs::OpenOptions;
 
@@ -143,3 +144,411 @@ fn save_multiple_files() {
     read_and_check!(path_file_2);
     read_and_check!(path_file_3);
 }
+
+#[test]
+fn save_leaves_no_temp_file() {
+    let val: Value = json!({
+        "hello": "world",
+        "key1": "hello_world"
+    });
+
+    let temp_dir = tempfile::tempdir().unwrap(); // Setting up temp dir
+    let path = temp_dir.path().join("file.json"); // Setting up the file to write to
+    write_val!(path, val);
+    {
+        let mut sparse_root: SparseRoot<SimpleStruct1> =
+            SparseRoot::new_from_file(path.clone()).unwrap();
+        let mut hello: SparseValueMut<SimpleStruct1> = sparse_root.root_get_mut().unwrap();
+
+        hello.hello = "toto".to_string();
+        hello.sparse_save().unwrap();
+        sparse_root.sparse_updt().unwrap();
+        sparse_root.save_to_disk(None).unwrap();
+    }
+    let files: Vec<PathBuf> = std::fs::read_dir(temp_dir.path())
+        .unwrap()
+        .map(|x| x.unwrap().path())
+        .collect();
+    assert_eq!(files, vec![path], "Only the saved file should remain");
+}
+
+#[test]
+fn save_failure_keeps_files() {
+    let val: Value = json!({
+        "hello": "world",
+        "key1":
+        {
+            "$ref": "other/file2.json#/key1"
+        }
+    });
+    let val2: Value = json!({
+        "key1": "hallo!"
+    });
+    let temp_dir = tempfile::tempdir().unwrap(); // Setting up temp dir
+    let path_file_1 = temp_dir.path().join("file.json"); // Setting up the file to write to
+    let path_file_2 = temp_dir.path().join("other").join("file2.json"); // Setting up the file to write to
+    std::fs::create_dir(temp_dir.path().join("other")).unwrap();
+    write_val!(path_file_1, val);
+    write_val!(path_file_2, val2);
+    {
+        let mut sparse_root: SparseRoot<SimpleStruct1> =
+            SparseRoot::new_from_file(path_file_1.clone()).unwrap();
+        let state = sparse_root.state().clone();
+        let mut hello: SparseValueMut<SimpleStruct1> = sparse_root.root_get_mut().unwrap();
+
+        hello.hello = "toto".to_string();
+        hello.sparse_save().unwrap();
+        let mut key = hello.key1.get_mut(state).unwrap();
+        *key = "unbelievable".to_string();
+        key.sparse_save().unwrap();
+        sparse_root.sparse_updt().unwrap();
+        // The second file can't be written anymore
+        std::fs::remove_dir_all(temp_dir.path().join("other")).unwrap();
+        sparse_root
+            .save_to_disk(Some(SparseFileFormat::Json(true)))
+            .expect_err("Supposed to fail, a directory is missing");
+    }
+    let files: Vec<PathBuf> = std::fs::read_dir(temp_dir.path())
+        .unwrap()
+        .map(|x| x.unwrap().path())
+        .collect();
+    assert_eq!(
+        files,
+        vec![path_file_1.clone()],
+        "The temporary files should be removed"
+    );
+    let file_base_read = OpenOptions::new().read(true).open(path_file_1).unwrap();
+    let saved: Value = serde_json::from_reader(file_base_read).unwrap();
+    assert_eq!(saved, val, "The original file should be untouched");
+}
+
+#[test]
+fn save_rename_failure_keeps_state() {
+    let temp_dir = tempfile::tempdir().unwrap(); // Setting up temp dir
+    let path_file_1 = temp_dir.path().join("file_a.json"); // Setting up the file to write to
+    let path_file_2 = temp_dir.path().join("file_b.json"); // Setting up the file to write to
+    std::fs::write(
+        &path_file_1,
+        "{\"hello\": \"world\", \"key1\": {\"$ref\": \"file_b.json#/key1\"}}",
+    )
+    .unwrap();
+    std::fs::write(&path_file_2, "{\"key1\": \"hallo!\"}").unwrap();
+    let mut state = SparseState::new_from_file(path_file_1.clone()).unwrap();
+    let _root: SimpleStruct1 = state.parse_root().unwrap();
+    state
+        .get_state_file_mut(&path_file_1)
+        .unwrap()
+        .replace(json!({ "hello": "toto", "key1": { "$ref": "file_b.json#/key1" } }));
+    state
+        .get_state_file_mut(&path_file_2)
+        .unwrap()
+        .replace(json!({ "key1": "unbelievable" }));
+    // The second file can't be replaced anymore
+    std::fs::remove_file(&path_file_2).unwrap();
+    std::fs::create_dir(&path_file_2).unwrap();
+    std::fs::write(path_file_2.join("blocker"), "").unwrap();
+    state
+        .save_to_disk(None)
+        .expect_err("Supposed to fail, a directory is in the way");
+
+    let mut files: Vec<PathBuf> = std::fs::read_dir(temp_dir.path())
+        .unwrap()
+        .map(|x| x.unwrap().path())
+        .collect();
+    files.sort();
+    assert_eq!(
+        files,
+        vec![path_file_1.clone(), path_file_2.clone()],
+        "The temporary files should be removed"
+    );
+    let saved: Value =
+        serde_json::from_str(&std::fs::read_to_string(&path_file_1).unwrap()).unwrap();
+    assert_eq!(
+        saved,
+        json!({ "hello": "toto", "key1": { "$ref": "file_b.json#/key1" } }),
+        "The file renamed before the failure should be saved"
+    );
+    assert!(
+        !state.get_state_file(&path_file_1).unwrap().dirty(),
+        "The saved file shouldn't be dirty anymore"
+    );
+    assert!(
+        state.get_state_file(&path_file_2).unwrap().dirty(),
+        "The file that failed to be saved should still be dirty"
+    );
+}
+
+#[test]
+fn save_only_dirty_files() {
+    let val: Value = json!({
+        "hello": "world",
+        "key1":
+        {
+            "$ref": "file2.json#/key1"
+        }
+    });
+    let val2: Value = json!({
+        "key1": "hallo!"
+    });
+    let temp_dir = tempfile::tempdir().unwrap(); // Setting up temp dir
+    let path_file_1 = temp_dir.path().join("file.json"); // Setting up the file to write to
+    let path_file_2 = temp_dir.path().join("file2.json"); // Setting up the file to write to
+    write_val!(path_file_1, val);
+    write_val!(path_file_2, val2);
+    let raw_file_1 = std::fs::read(&path_file_1).unwrap();
+    {
+        let mut sparse_root: SparseRoot<SimpleStruct1> =
+            SparseRoot::new_from_file(path_file_1.clone()).unwrap();
+        assert_eq!(
+            sparse_root.save_to_disk(None).unwrap(),
+            Vec::<PathBuf>::new(),
+            "Nothing should be written before a modification"
+        );
+        let state = sparse_root.state().clone();
+        let mut hello: SparseValueMut<SimpleStruct1> = sparse_root.root_get_mut().unwrap();
+
+        let mut key = hello.key1.get_mut(state).unwrap();
+        *key = "unbelievable".to_string();
+        key.sparse_save().unwrap();
+        sparse_root.sparse_updt().unwrap();
+        assert_eq!(
+            sparse_root
+                .save_to_disk(Some(SparseFileFormat::Json(true)))
+                .unwrap(),
+            vec![path_file_2.clone()],
+            "Only the modified file should be written"
+        );
+        assert_eq!(
+            sparse_root.save_to_disk(None).unwrap(),
+            Vec::<PathBuf>::new(),
+            "The saved file shouldn't be dirty anymore"
+        );
+    }
+    assert_eq!(
+        std::fs::read(&path_file_1).unwrap(),
+        raw_file_1,
+        "The untouched file should be left as is"
+    );
+    read_and_check!(path_file_2);
+}
+
+#[test]
+fn save_yaml_keeps_comments() {
+    let raw = "# The greeting\nhello: 'world' # Don't change the quotes\nkey1:\n  $ref: \"#/hello\" # A reference\n";
+
+    let temp_dir = tempfile::tempdir().unwrap(); // Setting up temp dir
+    let path = temp_dir.path().join("file.yaml"); // Setting up the file to write to
+    std::fs::write(&path, raw).unwrap();
+    {
+        let mut sparse_root: SparseRoot<SimpleStruct1> =
+            SparseRoot::new_from_file(path.clone()).unwrap();
+        let mut hello: SparseValueMut<SimpleStruct1> = sparse_root.root_get_mut().unwrap();
+
+        hello.hello = "it's me".to_string();
+        hello.sparse_save().unwrap();
+        sparse_root.sparse_updt().unwrap();
+        sparse_root.save_to_disk(None).unwrap();
+    }
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        raw.replace("'world'", "'it''s me'"),
+        "Only the modified value should change"
+    );
+}
+
+#[test]
+fn save_json_keeps_formatting() {
+    let raw =
+        "{\n    \"hello\": \"world\",\n    \"key1\": { \"$ref\": \"file2.json#/list/1\" }\n}\n";
+    let raw2 = "{\"list\": [1,   \"hallo!\" ,3], \"key1\": true}";
+
+    let temp_dir = tempfile::tempdir().unwrap(); // Setting up temp dir
+    let path_file_1 = temp_dir.path().join("file.json"); // Setting up the file to write to
+    let path_file_2 = temp_dir.path().join("file2.json"); // Setting up the file to write to
+    std::fs::write(&path_file_1, raw).unwrap();
+    std::fs::write(&path_file_2, raw2).unwrap();
+    {
+        let mut sparse_root: SparseRoot<SimpleStruct1> =
+            SparseRoot::new_from_file(path_file_1.clone()).unwrap();
+        let state = sparse_root.state().clone();
+        let mut hello: SparseValueMut<SimpleStruct1> = sparse_root.root_get_mut().unwrap();
+
+        let mut key = hello.key1.get_mut(state).unwrap();
+        *key = "unbelievable".to_string();
+        key.sparse_save().unwrap();
+        sparse_root.sparse_updt().unwrap();
+        sparse_root.save_to_disk(None).unwrap();
+    }
+    assert_eq!(
+        std::fs::read_to_string(&path_file_2).unwrap(),
+        raw2.replace("\"hallo!\"", "\"unbelievable\""),
+        "Only the modified value should change"
+    );
+}
+
+#[derive(Serialize, Deserialize, SparsableInner, Debug)]
+struct OrderedStruct {
+    zeta: u32,
+    hello: String,
+    obj: IndexMap<String, SparseSelector<String>>,
+    alpha: u32,
+}
+
+#[test]
+fn save_keeps_key_order() {
+    let raw = "zeta: 1\nhello: world\nobj:\n  zeta:\n    $ref: \"#/hello\"\n  alpha: universe\nalpha: 2\n";
+
+    let temp_dir = tempfile::tempdir().unwrap(); // Setting up temp dir
+    let path = temp_dir.path().join("file.yaml"); // Setting up the file to write to
+    std::fs::write(&path, raw).unwrap();
+    {
+        let mut sparse_root: SparseRoot<OrderedStruct> =
+            SparseRoot::new_from_file(path.clone()).unwrap();
+        let mut root: SparseValueMut<OrderedStruct> = sparse_root.root_get_mut().unwrap();
+
+        // The added key is appended to its mapping
+        root.obj.insert(
+            "mid".to_string(),
+            SparseSelector::Obj(SparsePointedValue::Obj("galaxy".to_string())),
+        );
+        root.sparse_save().unwrap();
+        sparse_root.sparse_updt().unwrap();
+        sparse_root.save_to_disk(None).unwrap();
+    }
+    let saved: Value = serde_yaml::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    let keys: Vec<&String> = saved.as_object().unwrap().keys().collect();
+    assert_eq!(
+        keys,
+        vec!["zeta", "hello", "obj", "alpha"],
+        "Root order mismatch"
+    );
+    let keys: Vec<&String> = saved["obj"].as_object().unwrap().keys().collect();
+    assert_eq!(keys, vec!["zeta", "alpha", "mid"], "Nested order mismatch");
+}
+
+#[derive(Serialize, Deserialize, SparsableInner, Debug)]
+struct ResizedStruct {
+    hello: String,
+    obj: IndexMap<String, u32>,
+    list: Vec<u32>,
+}
+
+const RESIZED_RAW: &str = "# The greeting\nhello: world # A comment\nobj:\n  # The first key\n  one: 1 # One\n  two: 2\n\n  # The last key\n  three: 3\nlist:\n  - 1 # One\n  - 2\n# The end\n";
+
+fn save_resized(resize: impl FnOnce(&mut ResizedStruct)) -> String {
+    let temp_dir = tempfile::tempdir().unwrap(); // Setting up temp dir
+    let path = temp_dir.path().join("file.yaml"); // Setting up the file to write to
+    std::fs::write(&path, RESIZED_RAW).unwrap();
+    {
+        let mut sparse_root: SparseRoot<ResizedStruct> =
+            SparseRoot::new_from_file(path.clone()).unwrap();
+        let mut root: SparseValueMut<ResizedStruct> = sparse_root.root_get_mut().unwrap();
+
+        resize(&mut root);
+        root.sparse_save().unwrap();
+        sparse_root.sparse_updt().unwrap();
+        sparse_root.save_to_disk(None).unwrap();
+    }
+    std::fs::read_to_string(&path).unwrap()
+}
+
+#[test]
+fn save_yaml_add_keeps_comments() {
+    assert_eq!(
+        save_resized(|root| {
+            root.obj.insert("four".to_string(), 4);
+            root.list.push(3);
+        }),
+        RESIZED_RAW
+            .replace("  three: 3\n", "  three: 3\n  four: 4\n")
+            .replace("  - 2\n", "  - 2\n  - 3\n"),
+        "The added key and item should be appended, keeping the comments"
+    );
+}
+
+#[test]
+fn save_yaml_remove_keeps_comments() {
+    assert_eq!(
+        save_resized(|root| {
+            root.obj.shift_remove("two");
+            root.list.truncate(1);
+        }),
+        RESIZED_RAW.replace("  two: 2\n", "").replace("  - 2\n", ""),
+        "Only the lines of the removed key and item should be removed"
+    );
+}
+
+#[test]
+fn save_lossless_fails() {
+    let raw = "# The greeting\nhello: world\nobj: {one: 1}\nlist: [1]\n";
+
+    let temp_dir = tempfile::tempdir().unwrap(); // Setting up temp dir
+    let path = temp_dir.path().join("file.yaml"); // Setting up the file to write to
+    std::fs::write(&path, raw).unwrap();
+    let mut sparse_root: SparseRoot<ResizedStruct> = SparseRoot::builder()
+        .lossless(true)
+        .build_from_file(path.clone())
+        .unwrap();
+    let mut root: SparseValueMut<ResizedStruct> = sparse_root.root_get_mut().unwrap();
+
+    // Flow mappings can't be patched
+    root.obj.insert("two".to_string(), 2);
+    root.sparse_save().unwrap();
+    sparse_root.sparse_updt().unwrap();
+    match sparse_root
+        .save_to_disk(None)
+        .expect_err("The file can't be saved without losing its comments")
+    {
+        SparseError::NotPatchable(_) => (),
+        _ => panic!("Expected the err to be `NotPatchable`"),
+    }
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        raw,
+        "The file should be left untouched"
+    );
+}
+
+#[test]
+fn save_refreshes_state_file() {
+    let raw = "---\nhello: world\nkey1:\n  $ref: \"#/hello\"\n---\nhello: universe\n";
+
+    let temp_dir = tempfile::tempdir().unwrap(); // Setting up temp dir
+    let path = temp_dir.path().join("file.yaml"); // Setting up the file to write to
+    std::fs::write(&path, raw).unwrap();
+    let mut state = SparseState::new_from_file(path.clone()).unwrap();
+    state
+        .get_state_file_mut(&path)
+        .unwrap()
+        .replace(json!([{ "hello": "world" }, { "hello": "galaxy" }]));
+    let version = state.get_state_file(&path).unwrap().version();
+    state
+        .save_to_disk(Some(SparseFileFormat::Json(true)))
+        .unwrap();
+
+    let state_file = state.get_state_file(&path).unwrap();
+    assert_eq!(
+        state_file.raw().as_deref(),
+        Some(std::fs::read_to_string(&path).unwrap().as_str()),
+        "The saved text should be kept"
+    );
+    assert!(!state_file.dirty(), "The saved file shouldn't be dirty");
+    assert!(!state_file.stream(), "A JSON file isn't a stream");
+    assert_eq!(
+        state_file.indent().as_deref(),
+        Some("  "),
+        "The indentation should be the saved one"
+    );
+    assert_eq!(
+        state_file
+            .span(&JsonPointer::parse("/1/hello").unwrap())
+            .map(|span| span.line()),
+        Some(6),
+        "The spans should be the saved ones"
+    );
+    assert_eq!(
+        state_file.version(),
+        version,
+        "The version should be kept, the value didn't change"
+    );
+}
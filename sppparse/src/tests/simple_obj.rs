@@ -33,9 +33,52 @@ fn wrong_type() {
 
     let parsed = state.parse_root::<SimpleStruct1>();
 
-    if let Err(SparseError::SerdeJson(_err)) = parsed {
-        // Ok
-    } else {
-        panic!("Should've failed at deserialization");
+    match parsed {
+        Err(SparseError::Deserialize {
+            file,
+            pointer,
+            path,
+            ..
+        }) => {
+            assert!(file.ends_with("hello.json"), "The file should be reported");
+            assert_eq!(pointer, "", "The pointer should be the root");
+            assert_eq!(path, "key1", "The serde path should be reported");
+        }
+        _ => panic!("Should've failed at deserialization"),
+    }
+}
+
+#[test]
+fn wrong_type_distant() {
+    let val: Value = json!({
+        "hello": "world",
+        "key1": {
+            "$ref": "other.json#/nested/key1"
+        }
+    });
+    let val2: Value = json!({
+        "nested": {
+            "key1": ["not", "a", "string"]
+        }
+    });
+
+    let parsed = SparseRoot::<SimpleStruct1>::new_from_value(
+        val,
+        PathBuf::from_str("hello.json").unwrap(),
+        vec![(val2, PathBuf::from_str("other.json").unwrap())],
+    );
+
+    match parsed.map_err(|err| match err {
+        SparseError::BrokenRef { source, .. } => *source,
+        err => err,
+    }) {
+        Err(SparseError::Deserialize { file, pointer, .. }) => {
+            assert!(file.ends_with("other.json"), "The file should be reported");
+            assert_eq!(
+                pointer, "/nested/key1",
+                "The pointer should be the reference's"
+            );
+        }
+        _ => panic!("Should've failed at deserialization"),
     }
 }
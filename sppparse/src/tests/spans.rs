@@ -0,0 +1,143 @@
+use super::*;
+use serde_json::json;
+use std::fs::File;
+use std::io::Write;
+use std::str::FromStr;
+
+fn write_doc(dir: &tempfile::TempDir, name: &str, content: &str) -> PathBuf {
+    let path = dir.path().join(name);
+    let mut file = File::create(&path).unwrap();
+    file.write_all(content.as_bytes()).unwrap();
+    path
+}
+
+#[test]
+fn json() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_doc(
+        &dir,
+        "root.json",
+        "{\n  \"hello\": \"world\",\n  \"list\": [1, {\"$ref\": \"#/hello\"}]\n}\n",
+    );
+    let state = SparseState::new_from_file(path.clone()).unwrap();
+    let state_file = state.get_state_file(&path).unwrap();
+
+    let span = state_file
+        .span(&JsonPointer::parse("/list/1/$ref").unwrap())
+        .expect("the span of the reference");
+    assert_eq!(span.line(), 3, "Line mismatch");
+    assert_eq!(span.column(), 24, "Column mismatch");
+    assert_eq!(span.offset(), 45, "Offset mismatch");
+    let span = state_file
+        .span(&JsonPointer::new())
+        .expect("the span of the root");
+    assert_eq!((span.line(), span.column()), (1, 1), "Root mismatch");
+}
+
+#[test]
+fn yaml() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_doc(
+        &dir,
+        "root.yaml",
+        "hello: world\nlist:\n  - 1\n  - $ref: \"#/hello\"\n",
+    );
+    let state = SparseState::new_from_file(path.clone()).unwrap();
+    let state_file = state.get_state_file(&path).unwrap();
+
+    let span = state_file
+        .span(&JsonPointer::parse("/list/1/$ref").unwrap())
+        .expect("the span of the reference");
+    assert_eq!(span.line(), 4, "Line mismatch");
+    assert_eq!(span.column(), 11, "Column mismatch");
+    assert_eq!(span.offset(), 35, "Offset mismatch");
+    let span = state_file
+        .span(&JsonPointer::parse("/hello").unwrap())
+        .expect("the span of a scalar");
+    assert_eq!((span.line(), span.column()), (1, 8), "Scalar mismatch");
+}
+
+#[test]
+fn broken_ref() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_doc(
+        &dir,
+        "root.yaml",
+        "hello: world\nkey1:\n  $ref: \"#/not/here\"\n",
+    );
+    let err = SparseRoot::<SimpleStruct1>::new_from_file(path).expect_err("a dangling pointer");
+
+    match err {
+        SparseError::BrokenRef { location, source } => {
+            assert!(location.file().ends_with("root.yaml"), "File mismatch");
+            assert_eq!(location.pointer(), "/key1/$ref", "Pointer mismatch");
+            let span = location.span().expect("the span of the reference");
+            assert_eq!((span.line(), span.column()), (3, 9), "Span mismatch");
+            assert!(
+                location.to_string().ends_with("root.yaml:3:9"),
+                "The location should be displayed as `file:line:column`"
+            );
+            match *source {
+                SparseError::UnkownPath(_) => (),
+                _ => panic!("Expected the source to be `UnkownPath`"),
+            }
+        }
+        _ => panic!("Expected the err to be `BrokenRef`"),
+    }
+}
+
+#[test]
+fn in_memory() {
+    let val: Value = json!({
+        "hello": "world",
+        "key1": {
+            "$ref": "#/not/here"
+        }
+    });
+    let err = SparseRoot::<SimpleStruct1>::new_from_value(
+        val,
+        PathBuf::from_str("hello.json").unwrap(),
+        vec![],
+    )
+    .expect_err("a dangling pointer");
+
+    match err {
+        SparseError::BrokenRef { location, .. } => {
+            assert_eq!(location.pointer(), "/key1/$ref", "Pointer mismatch");
+            assert_eq!(location.span(), &None, "There is no text to point to");
+            assert!(
+                location.to_string().ends_with("hello.json#/key1/$ref"),
+                "The location should fall back to the pointer"
+            );
+        }
+        _ => panic!("Expected the err to be `BrokenRef`"),
+    }
+}
+
+#[test]
+fn duplicate_refs() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_doc(
+        &dir,
+        "root.yaml",
+        "hello: world\nlist: []\nkey1:\n  $ref: \"#/hello\"\nkey2:\n  $ref: \"#/hello\"\nkey3:\n  $ref: \"#/hello\"\n",
+    );
+    let root = SparseRoot::<SimpleStruct3>::new_from_file(path).unwrap();
+    let val = root.root_get().unwrap();
+
+    for (selector, line) in [(val.key1(), 4), (val.key2(), 6), (val.key3(), 8)].iter() {
+        let location = match selector {
+            SparseSelector::Ref(x) => match x.val() {
+                SparsePointedValue::Ref(x) => x.utils().location().cloned(),
+                _ => None,
+            },
+            _ => None,
+        }
+        .expect("the location of the reference");
+        assert_eq!(
+            location.span().map(|span| span.line()),
+            Some(*line),
+            "Each reference should be located where it lives"
+        );
+    }
+}
@@ -0,0 +1,69 @@
+use super::*;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::thread;
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn send_sync() {
+    assert_send_sync::<SparseState>();
+    assert_send_sync::<SparseRoot<SimpleStruct3>>();
+    assert_send_sync::<SparseRefLazy<String>>();
+}
+
+#[test]
+fn threads() {
+    let root: Arc<SparseRoot<SimpleStruct3>> = Arc::new(
+        SparseRoot::new_from_file(
+            PathBuf::from_str(sparse_test_rel_path!("./src/tests/docs/list.json")).unwrap(),
+        )
+        .unwrap(),
+    );
+    let handles: Vec<thread::JoinHandle<String>> = (0..4)
+        .map(|_| {
+            let root = root.clone();
+            thread::spawn(move || (*root.root_get().unwrap().key2.get().unwrap()).clone())
+        })
+        .collect();
+
+    for handle in handles.into_iter() {
+        assert_eq!(
+            handle.join().unwrap(),
+            "world",
+            "The dereferenced value doesn't match"
+        );
+    }
+}
+
+#[test]
+fn lazy_threads() {
+    let lazy: Arc<SparseRefLazy<String>> = Arc::new(
+        SparseRefLazy::new(
+            PathBuf::from_str(sparse_test_rel_path!("./src/tests/docs/simple.json")).unwrap(),
+            String::from("#/hello"),
+        )
+        .unwrap(),
+    );
+    let state: SparseStateCell = SparseRc::new(SparseCell::new(
+        SparseState::new_from_file(
+            PathBuf::from_str(sparse_test_rel_path!("./src/tests/docs/simple.json")).unwrap(),
+        )
+        .unwrap(),
+    ));
+    let handles: Vec<thread::JoinHandle<String>> = (0..4)
+        .map(|_| {
+            let lazy = lazy.clone();
+            let state = state.clone();
+            thread::spawn(move || (*lazy.get(state).unwrap()).clone())
+        })
+        .collect();
+
+    for handle in handles.into_iter() {
+        assert_eq!(
+            handle.join().unwrap(),
+            "world",
+            "The dereferenced value doesn't match"
+        );
+    }
+}
@@ -0,0 +1,83 @@
+use super::*;
+
+#[test]
+fn toml_root() {
+    let temp_dir = tempfile::tempdir().unwrap(); // Setting up temp dir
+    let path_file_1 = temp_dir.path().join("root.toml");
+    std::fs::write(
+        &path_file_1,
+        "hello = \"world\"\n\n[key1]\n\"$ref\" = \"other.yaml#/greeting\"\n",
+    )
+    .unwrap();
+    std::fs::write(temp_dir.path().join("other.yaml"), "greeting: universe\n").unwrap();
+
+    let sparse_root: SparseRoot<SimpleStruct1> = SparseRoot::new_from_file(path_file_1).unwrap();
+    let root = sparse_root.root_get().unwrap();
+    assert_eq!(root.hello(), "world", "The TOML value doesn't match");
+    assert_eq!(
+        *root.key1().get().unwrap(),
+        "universe",
+        "The dereferenced YAML value doesn't match"
+    );
+}
+
+#[test]
+fn toml_distant() {
+    let temp_dir = tempfile::tempdir().unwrap(); // Setting up temp dir
+    let path_file_1 = temp_dir.path().join("root.yaml");
+    let path_file_2 = temp_dir.path().join("other.toml");
+    std::fs::write(
+        &path_file_1,
+        "hello: world\nkey1:\n  $ref: \"other.toml#/section/greeting\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        &path_file_2,
+        "title = \"other\"\n\n[section]\ngreeting = \"universe\"\n",
+    )
+    .unwrap();
+    {
+        let mut sparse_root: SparseRoot<SimpleStruct1> =
+            SparseRoot::new_from_file(path_file_1).unwrap();
+        {
+            let state = sparse_root.state().try_borrow_state().unwrap();
+            assert_eq!(
+                state.get_state_file(&path_file_2).unwrap().ftype(),
+                SparseFileFormat::Toml,
+                "The referenced file should be read as TOML"
+            );
+        }
+        let state = sparse_root.state().clone();
+        let mut hello: SparseValueMut<SimpleStruct1> = sparse_root.root_get_mut().unwrap();
+
+        let mut key = hello.key1.get_mut(state).unwrap();
+        *key = "galaxy".to_string();
+        key.sparse_save().unwrap();
+        sparse_root.sparse_updt().unwrap();
+        sparse_root.save_to_disk(None).unwrap();
+    }
+    assert_eq!(
+        std::fs::read_to_string(&path_file_2).unwrap(),
+        "title = \"other\"\n\n[section]\ngreeting = \"galaxy\"\n",
+        "The referenced file should be saved as TOML"
+    );
+}
+
+#[test]
+fn toml_sniff() {
+    assert_eq!(
+        SparseFileFormat::sniff(b"# only a comment\n").unwrap(),
+        SparseFileFormat::Yaml,
+        "A document made of comments should be YAML"
+    );
+    assert_eq!(
+        SparseFileFormat::sniff(b"# a comment\nhello = \"world\"\n").unwrap(),
+        SparseFileFormat::Toml,
+        "A key should be TOML"
+    );
+    assert_eq!(
+        SparseFileFormat::sniff(b"[table]\n").unwrap(),
+        SparseFileFormat::Toml,
+        "A table should be TOML"
+    );
+}
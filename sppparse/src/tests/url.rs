@@ -0,0 +1,179 @@
+use super::*;
+use std::str::FromStr;
+use url_inner::Url;
+
+fn url_loader() -> SparseRc<dyn SparseLoader> {
+    SparseRc::new(SparseUrlDirLoader::new().mount(
+        Url::parse("https://example.com/schemas/").unwrap(),
+        PathBuf::from(sparse_test_rel_path!("./src/tests/docs/")),
+    ))
+}
+
+#[test]
+fn parse_absolute_url() {
+    let r = SparseMetadata::new_with_url(
+        "https://example.com/schemas/pet.yaml#/Pet".to_string(),
+        PathBuf::from(sparse_test_rel_path!("./examples/selector.json")),
+        None,
+    )
+    .unwrap();
+
+    assert_eq!(
+        r.pfile_url(),
+        &Some(Url::parse("https://example.com/schemas/pet.yaml").unwrap()),
+        "The URL mismatch"
+    );
+    assert_eq!(r.pointer(), "/Pet", "pointers mismatch");
+}
+
+#[test]
+fn parse_relative_url() {
+    let base = Url::parse("https://example.com/schemas/pet.yaml").unwrap();
+    let distant = SparseMetadata::new_with_url(
+        "./owner.yaml#/Owner".to_string(),
+        PathBuf::from(sparse_test_rel_path!("./examples/selector.json")),
+        Some(&base),
+    )
+    .unwrap();
+    let local = SparseMetadata::new_with_url(
+        "#/Pet".to_string(),
+        PathBuf::from(sparse_test_rel_path!("./examples/selector.json")),
+        Some(&base),
+    )
+    .unwrap();
+
+    assert_eq!(
+        distant.pfile_url(),
+        &Some(Url::parse("https://example.com/schemas/owner.yaml").unwrap()),
+        "The URL mismatch"
+    );
+    assert_eq!(local.pfile_url(), &Some(base), "The URL mismatch");
+}
+
+#[test]
+fn parse_local_path() {
+    let r = SparseMetadata::new_with_url(
+        "./world.json#/hello".to_string(),
+        PathBuf::from(sparse_test_rel_path!("./examples/selector.json")),
+        None,
+    )
+    .unwrap();
+
+    assert_eq!(r.pfile_url(), &None, "It shouldn't be a URL");
+}
+
+#[test]
+fn distant_url() {
+    let root: SparseRoot<SimpleStruct3> = SparseRoot::new_from_file_with_loader(
+        PathBuf::from_str(sparse_test_rel_path!("./src/tests/docs/url.json")).unwrap(),
+        url_loader(),
+    )
+    .unwrap();
+    let val = root.root_get().unwrap();
+
+    assert_eq!(val.key1.get().unwrap().as_str(), "world");
+    assert_eq!(val.key2.get().unwrap().as_str(), "world");
+    assert_eq!(val.key3.get().unwrap().as_str(), "world");
+    let state = root.state().try_borrow_state().unwrap();
+    for url in [
+        "https://example.com/schemas/list.json",
+        "https://example.com/schemas/simple.json",
+    ]
+    .iter()
+    {
+        state
+            .get_state_file_url(&Url::parse(url).unwrap())
+            .expect("The distant document should be in the state");
+    }
+}
+
+#[test]
+fn distant_url_no_loader() {
+    let err = SparseRoot::<SimpleStruct3>::new_from_file(
+        PathBuf::from_str(sparse_test_rel_path!("./src/tests/docs/url.json")).unwrap(),
+    )
+    .expect_err("The default loader can't fetch URLs");
+
+    match err {
+        SparseError::BrokenRef { source, .. } => match *source {
+            SparseError::UnsupportedUrl(_) => (),
+            _ => panic!("Expected the err to be `UnsupportedUrl`"),
+        },
+        _ => panic!("Expected the err to be `BrokenRef`"),
+    };
+}
+
+#[test]
+fn dir_loader_segment_boundary() {
+    let loader = SparseUrlDirLoader::new().mount(
+        Url::parse("https://example.com/schemas").unwrap(),
+        PathBuf::from(sparse_test_rel_path!("./src/tests/docs/")),
+    );
+
+    loader
+        .load_url(&Url::parse("https://example.com/schemas/simple.json").unwrap())
+        .expect("The base URL should be a directory");
+    match loader.load_url(&Url::parse("https://example.com/schemas-old/simple.json").unwrap()) {
+        Err(SparseError::UnsupportedUrl(_)) => (),
+        _ => panic!("Expected the err to be `UnsupportedUrl`"),
+    };
+    match loader.load_url(&Url::parse("https://example.org/schemas/simple.json").unwrap()) {
+        Err(SparseError::UnsupportedUrl(_)) => (),
+        _ => panic!("Expected the err to be `UnsupportedUrl`"),
+    };
+}
+
+#[test]
+fn dir_loader_percent_decoding() {
+    let temp_dir = tempfile::tempdir().unwrap(); // Setting up temp dir
+    let mirror_dir = temp_dir.path().join("mirror");
+    std::fs::create_dir(&mirror_dir).unwrap();
+    std::fs::write(mirror_dir.join("my pet.json"), "{\"hello\": \"world\"}").unwrap();
+    std::fs::write(
+        temp_dir.path().join("secret.json"),
+        "{\"hello\": \"secret\"}",
+    )
+    .unwrap();
+    let loader = SparseUrlDirLoader::new().mount(
+        Url::parse("https://example.com/schemas/").unwrap(),
+        mirror_dir,
+    );
+
+    let (raw, _format) = loader
+        .load_url(&Url::parse("https://example.com/schemas/my%20pet.json").unwrap())
+        .expect("The path should be percent-decoded");
+    assert_eq!(raw, b"{\"hello\": \"world\"}", "The file mismatch");
+    match loader.load_url(&Url::parse("https://example.com/schemas/..%2Fsecret.json").unwrap()) {
+        Err(SparseError::UnsupportedUrl(_)) => (),
+        _ => panic!("A decoded segment shouldn't escape the directory"),
+    };
+}
+
+#[test]
+fn save_modified_distant() {
+    let temp_dir = tempfile::tempdir().unwrap(); // Setting up temp dir
+    let path = temp_dir.path().join("url.json");
+    std::fs::copy(sparse_test_rel_path!("./src/tests/docs/url.json"), &path).unwrap();
+    let raw = std::fs::read(&path).unwrap();
+    let mut state = SparseState::new_from_file_with_loader(path.clone(), url_loader()).unwrap();
+    let _root: SimpleStruct3 = state.parse_root().unwrap();
+    let url = Url::parse("https://example.com/schemas/simple.json").unwrap();
+    state
+        .get_state_file_url_mut(&url)
+        .unwrap()
+        .replace(serde_json::json!({ "hello": "universe" }));
+    state
+        .get_state_file_mut(&path)
+        .unwrap()
+        .replace(serde_json::json!({ "list": [] }));
+
+    match state.save_to_disk(None) {
+        Err(SparseError::UnsavableUrl(x)) => assert_eq!(x, url.to_string(), "The URL mismatch"),
+        _ => panic!("Expected the err to be `UnsavableUrl`"),
+    };
+    assert_eq!(
+        std::fs::read(&path).unwrap(),
+        raw,
+        "Nothing should be written"
+    );
+}
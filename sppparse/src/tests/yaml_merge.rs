@@ -0,0 +1,47 @@
+use super::*;
+use serde_json::json;
+
+#[test]
+fn yaml_merge_keys() {
+    let temp_dir = tempfile::tempdir().unwrap(); // Setting up temp dir
+    let path_file_1 = temp_dir.path().join("root.yaml");
+    std::fs::write(
+        &path_file_1,
+        "base: &base\n  greeting: hello\n  name: world\nother: &other\n  name: galaxy\n  extra: true\nderived:\n  greeting: hi\n  <<: [*base, *other]\nalias: *base\n",
+    )
+    .unwrap();
+
+    let state = SparseState::new_from_file(path_file_1.clone()).unwrap();
+    assert_eq!(
+        state.get_state_file(&path_file_1).unwrap().val(),
+        &json!({
+            "base": {"greeting": "hello", "name": "world"},
+            "other": {"name": "galaxy", "extra": true},
+            "derived": {"greeting": "hi", "name": "world", "extra": true},
+            "alias": {"greeting": "hello", "name": "world"}
+        }),
+        "The merge keys and the aliases should be expanded"
+    );
+}
+
+#[test]
+fn yaml_merge_keys_ref() {
+    let temp_dir = tempfile::tempdir().unwrap(); // Setting up temp dir
+    let path_file_1 = temp_dir.path().join("root.yaml");
+    std::fs::write(
+        &path_file_1,
+        "defaults: &defaults\n  hello: world\n  key1:\n    $ref: \"#/defaults/hello\"\nbase:\n  <<: *defaults\n",
+    )
+    .unwrap();
+
+    let sparse_root: SparseRoot<HashMap<String, SimpleStruct1>> =
+        SparseRoot::new_from_file(path_file_1).unwrap();
+    let root = sparse_root.root_get().unwrap();
+    let base = root.get("base").expect("the merged mapping");
+    assert_eq!(base.hello(), "world", "The merged value doesn't match");
+    assert_eq!(
+        *base.key1().get().unwrap(),
+        "world",
+        "The merged reference should be resolved"
+    );
+}
@@ -0,0 +1,78 @@
+use super::*;
+use serde_json::json;
+
+const STREAM_DOC: &str =
+    "# The first document\ngreeting: hello\n---\n# The second document\ngreeting: world\n";
+
+#[test]
+fn yaml_stream_root() {
+    let temp_dir = tempfile::tempdir().unwrap(); // Setting up temp dir
+    let path_file_1 = temp_dir.path().join("stream.yaml");
+    std::fs::write(&path_file_1, STREAM_DOC).unwrap();
+
+    let state = SparseState::new_from_file(path_file_1.clone()).unwrap();
+    let state_file = state.get_state_file(&path_file_1).unwrap();
+    assert!(state_file.stream(), "The file should be read as a stream");
+    assert_eq!(
+        state_file.val(),
+        &json!([{"greeting": "hello"}, {"greeting": "world"}]),
+        "Every document should be read"
+    );
+    let span = state_file
+        .span(&JsonPointer::parse("/1/greeting").unwrap())
+        .expect("the span of a value of the second document");
+    assert_eq!((span.line(), span.column()), (5, 11), "Span mismatch");
+}
+
+#[test]
+fn yaml_stream_distant() {
+    let temp_dir = tempfile::tempdir().unwrap(); // Setting up temp dir
+    let path_file_1 = temp_dir.path().join("root.yaml");
+    let path_file_2 = temp_dir.path().join("stream.yaml");
+    std::fs::write(
+        &path_file_1,
+        "hello: world\nkey1:\n  $ref: \"stream.yaml#/1/greeting\"\n",
+    )
+    .unwrap();
+    std::fs::write(&path_file_2, STREAM_DOC).unwrap();
+    {
+        let mut sparse_root: SparseRoot<SimpleStruct1> =
+            SparseRoot::new_from_file(path_file_1).unwrap();
+        let state = sparse_root.state().clone();
+        let mut hello: SparseValueMut<SimpleStruct1> = sparse_root.root_get_mut().unwrap();
+
+        let mut key = hello.key1.get_mut(state).unwrap();
+        assert_eq!(*key, "world", "The dereferenced value doesn't match");
+        *key = "galaxy".to_string();
+        key.sparse_save().unwrap();
+        sparse_root.sparse_updt().unwrap();
+        sparse_root.save_to_disk(None).unwrap();
+    }
+    assert_eq!(
+        std::fs::read_to_string(&path_file_2).unwrap(),
+        STREAM_DOC.replace("world", "galaxy"),
+        "Only the modified value of the stream should be rewritten"
+    );
+}
+
+#[test]
+fn yaml_stream_save() {
+    let temp_dir = tempfile::tempdir().unwrap(); // Setting up temp dir
+    let path_file_1 = temp_dir.path().join("stream.yaml");
+    std::fs::write(&path_file_1, STREAM_DOC).unwrap();
+    {
+        let mut sparse_root: SparseRoot<Vec<Value>> =
+            SparseRoot::new_from_file(path_file_1.clone()).unwrap();
+        let mut docs: SparseValueMut<Vec<Value>> = sparse_root.root_get_mut().unwrap();
+
+        docs.push(json!({"greeting": "universe"}));
+        docs.sparse_save().unwrap();
+        sparse_root.sparse_updt().unwrap();
+        sparse_root.save_to_disk(None).unwrap();
+    }
+    assert_eq!(
+        std::fs::read_to_string(&path_file_1).unwrap(),
+        "---\ngreeting: hello\n---\ngreeting: world\n---\ngreeting: universe\n",
+        "The stream should be saved as multiple documents"
+    );
+}
@@ -12,6 +12,7 @@ fn sparsable_derive(mut s: synstructure::Structure) -> proc_macro2::TokenStream
     let crate_name = proc_macro_crate::crate_name("sppparse")
         .map(|v| syn::Ident::new(v.as_str(), proc_macro2::Span::call_site()));
     s.add_bounds(synstructure::AddBounds::Both);
+    s.underscore_const(true);
     match crate_name {
 		Ok(name) => {
 			s.gen_impl(quote! {
@@ -50,6 +51,7 @@ fn sparsable_derive_inner(mut s: synstructure::Structure) -> proc_macro2::TokenS
     });
 
     s.add_bounds(synstructure::AddBounds::Fields);
+    s.underscore_const(true);
     s.gen_impl(quote! {
         use crate::*;
         gen impl SparsableTrait for @Self {